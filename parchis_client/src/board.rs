@@ -0,0 +1,110 @@
+/// Geometry of the cross-shaped board drawn by the client.
+///
+/// The board is a 15x15 grid. Each color owns one corner house and walks the
+/// 52-square shared track clockwise from its start square. Board positions
+/// 1 to 51 are on the track, 52 to 57 are the color's home column and the
+/// goal sits in the middle.
+use crate::protocol::{PawnColor, PawnSpot};
+
+pub const BOARD_SIZE: usize = 15;
+
+const TRACK_LENGTH: usize = 52;
+const LAST_TRACK_POSITION: u8 = 51;
+const GOAL: (usize, usize) = (7, 7);
+
+/// The first quarter of the track, starting at Red's start square; the other
+/// three quarters are the same cells rotated around the goal.
+const FIRST_QUARTER: [(usize, usize); 13] = [
+    (6, 1), (6, 2), (6, 3), (6, 4), (6, 5),
+    (5, 6), (4, 6), (3, 6), (2, 6), (1, 6), (0, 6),
+    (0, 7), (0, 8),
+];
+
+/// Red's home column, from the entrance to the square next to the goal.
+const RED_HOME_COLUMN: [(usize, usize); 6] = [(7, 1), (7, 2), (7, 3), (7, 4), (7, 5), (7, 6)];
+
+/// Where the four pawns wait, relative to the top-left corner of their house.
+const HOUSE_SLOTS: [(usize, usize); 4] = [(2, 2), (2, 3), (3, 2), (3, 3)];
+const HOUSE_SIZE: usize = 6;
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Cell {
+    Blank,
+    Track,
+    Start(PawnColor),
+    HomeColumn(PawnColor),
+    House(PawnColor),
+    Goal,
+}
+
+/// Colors in clockwise order, starting from the top-left house.
+const QUARTER_COLORS: [PawnColor; 4] = [PawnColor::Red, PawnColor::Green, PawnColor::Yellow, PawnColor::Blue];
+
+fn quarter_of(color: PawnColor) -> Option<usize> {
+    QUARTER_COLORS.iter().position(|c| *c == color)
+}
+
+/// Rotates a cell clockwise around the goal by the given number of quarter turns.
+fn rotate((row, col): (usize, usize), quarters: usize) -> (usize, usize) {
+    (0..quarters % 4).fold((row, col), |(r, c), _| (c, BOARD_SIZE - 1 - r))
+}
+
+fn track_cell(index: usize) -> (usize, usize) {
+    let index = index % TRACK_LENGTH;
+    rotate(FIRST_QUARTER[index % FIRST_QUARTER.len()], index / FIRST_QUARTER.len())
+}
+
+/// The grid cell where a pawn should be drawn, or `None` for an unknown color.
+pub fn pawn_cell(color: PawnColor, spot: PawnSpot, pawn_index: usize) -> Option<(usize, usize)> {
+    let quarter = quarter_of(color)?;
+    let cell = match spot {
+        PawnSpot::House => {
+            // Houses are placed by rotation but their slots are not, so pawns
+            // read 1-2 / 3-4 in every corner.
+            let (corner_row, corner_col) = rotate((0, 0), quarter);
+            let origin_row = corner_row.min(BOARD_SIZE - HOUSE_SIZE);
+            let origin_col = corner_col.min(BOARD_SIZE - HOUSE_SIZE);
+            let (row, col) = HOUSE_SLOTS[pawn_index % HOUSE_SLOTS.len()];
+            (origin_row + row, origin_col + col)
+        }
+        PawnSpot::Board(position) if position <= LAST_TRACK_POSITION => {
+            track_cell(quarter * FIRST_QUARTER.len() + position.max(1) as usize - 1)
+        }
+        PawnSpot::Board(position) => {
+            let step = (position - LAST_TRACK_POSITION - 1) as usize;
+            rotate(RED_HOME_COLUMN[step.min(RED_HOME_COLUMN.len() - 1)], quarter)
+        }
+        PawnSpot::Goal => GOAL,
+    };
+    Some(cell)
+}
+
+/// What every grid cell represents, indexed by `[row][col]`.
+pub fn layout() -> [[Cell; BOARD_SIZE]; BOARD_SIZE] {
+    let mut grid = [[Cell::Blank; BOARD_SIZE]; BOARD_SIZE];
+
+    for (quarter, color) in QUARTER_COLORS.iter().enumerate() {
+        for row in 0..HOUSE_SIZE {
+            for col in 0..HOUSE_SIZE {
+                let (r, c) = rotate((row, col), quarter);
+                grid[r][c] = Cell::House(*color);
+            }
+        }
+        for cell in RED_HOME_COLUMN {
+            let (r, c) = rotate(cell, quarter);
+            grid[r][c] = Cell::HomeColumn(*color);
+        }
+    }
+
+    for index in 0..TRACK_LENGTH {
+        let (r, c) = track_cell(index);
+        grid[r][c] = if index % FIRST_QUARTER.len() == 0 {
+            Cell::Start(QUARTER_COLORS[index / FIRST_QUARTER.len()])
+        } else {
+            Cell::Track
+        };
+    }
+
+    grid[GOAL.0][GOAL.1] = Cell::Goal;
+    grid
+}
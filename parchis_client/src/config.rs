@@ -0,0 +1,85 @@
+use std::path::PathBuf;
+use parchis_core::game_state::validate_name;
+
+pub const USAGE: &str = "Usage: parchis_client [--host <host>] [--port <port>] [--name <name>]
+       parchis_client --local [--players <name,name,...>] [--bots <n>]
+       parchis_client --replay <file>
+       parchis_client --verify <file>";
+
+pub struct Config {
+    pub host: String,
+    pub port: u16,
+    /// When set, the client joins the game with this name as soon as it connects.
+    pub name: Option<String>,
+    /// Play a hot-seat game on this keyboard instead of connecting to a server.
+    pub local: bool,
+    /// Human players of a local game, in seating order.
+    pub players: Vec<String>,
+    pub bots: usize,
+    /// Replay file to step through instead of playing.
+    pub replay: Option<PathBuf>,
+    /// Replay file whose fair dice to check instead of playing.
+    pub verify: Option<PathBuf>,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Config {
+            host: "127.0.0.1".to_string(),
+            port: 7878,
+            name: None,
+            local: false,
+            players: vec!["Player1".to_string(), "Player2".to_string()],
+            bots: 0,
+            replay: None,
+            verify: None,
+        }
+    }
+}
+
+impl Config {
+    /// Parses the command-line arguments, not including the program name.
+    pub fn from_args(args: impl IntoIterator<Item = String>) -> Result<Config, String> {
+        let mut config = Config::default();
+        let mut args = args.into_iter();
+
+        while let Some(flag) = args.next() {
+            if flag == "--help" || flag == "-h" {
+                println!("{}", USAGE);
+                std::process::exit(0);
+            }
+            if flag == "--local" {
+                config.local = true;
+                continue;
+            }
+
+            let value = args.next().ok_or_else(|| format!("missing value for {}", flag))?;
+
+            match flag.as_str() {
+                "--host" => config.host = value,
+                "--port" => config.port = value.parse().map_err(|_| format!("invalid value '{}' for --port", value))?,
+                "--name" => {
+                    validate_name(&value).map_err(|e| format!("invalid value '{}' for --name: {}", value, e))?;
+                    config.name = Some(value);
+                }
+                "--players" => {
+                    let players: Vec<String> = value.split(',').map(str::to_string).collect();
+                    for name in &players {
+                        validate_name(name).map_err(|e| format!("invalid value '{}' for --players: {}", value, e))?;
+                    }
+                    config.players = players;
+                }
+                "--bots" => config.bots = value.parse().map_err(|_| format!("invalid value '{}' for --bots", value))?,
+                "--replay" => config.replay = Some(PathBuf::from(value)),
+                "--verify" => config.verify = Some(PathBuf::from(value)),
+                _ => return Err(format!("unknown option {}", flag)),
+            }
+        }
+
+        Ok(config)
+    }
+
+    pub fn address(&self) -> String {
+        format!("{}:{}", self.host, self.port)
+    }
+}
@@ -0,0 +1,98 @@
+use std::io::{self, BufRead, BufReader, Write};
+use std::net::TcpStream;
+use std::sync::mpsc::{self, Receiver, TryRecvError};
+use std::thread;
+use std::time::{Duration, Instant};
+
+const FIRST_RETRY: Duration = Duration::from_secs(1);
+const MAX_RETRY: Duration = Duration::from_secs(30);
+
+pub enum Incoming {
+    Message(String),
+    Empty,
+    Closed,
+}
+
+/// A live connection to the server. Messages are read on a background thread
+/// and split on the `END_OF_MESSAGE` marker.
+pub struct Connection {
+    stream: TcpStream,
+    incoming: Receiver<String>,
+}
+
+impl Connection {
+    pub fn open(address: &str) -> io::Result<Connection> {
+        let stream = TcpStream::connect(address)?;
+        let (tx, rx) = mpsc::channel();
+        let mut reader = BufReader::new(stream.try_clone()?);
+
+        thread::spawn(move || {
+            let mut message_accumulator = String::new();
+
+            loop {
+                let mut response = String::new();
+                match reader.read_line(&mut response) {
+                    Ok(0) | Err(_) => break,
+                    Ok(_) => {
+                        message_accumulator.push_str(&response);
+
+                        if message_accumulator.ends_with("END_OF_MESSAGE\n") {
+                            let clean_message = message_accumulator
+                                .replace("END_OF_MESSAGE\n", "");
+                            if tx.send(clean_message).is_err() {
+                                break;
+                            }
+                            message_accumulator.clear();
+                        }
+                    }
+                }
+            }
+        });
+
+        Ok(Connection { stream, incoming: rx })
+    }
+
+    pub fn send(&mut self, line: &str) -> io::Result<()> {
+        self.stream.write_all(format!("{}\n", line).as_bytes())
+    }
+
+    pub fn poll(&self) -> Incoming {
+        match self.incoming.try_recv() {
+            Ok(message) => Incoming::Message(message),
+            Err(TryRecvError::Empty) => Incoming::Empty,
+            Err(TryRecvError::Disconnected) => Incoming::Closed,
+        }
+    }
+}
+
+/// Exponential backoff between reconnection attempts.
+pub struct Reconnect {
+    attempt: u32,
+    delay: Duration,
+    next_try: Instant,
+}
+
+impl Reconnect {
+    pub fn new() -> Self {
+        Reconnect {
+            attempt: 1,
+            delay: FIRST_RETRY,
+            next_try: Instant::now() + FIRST_RETRY,
+        }
+    }
+
+    pub fn is_due(&self) -> bool {
+        Instant::now() >= self.next_try
+    }
+
+    pub fn failed(&mut self) {
+        self.attempt += 1;
+        self.delay = (self.delay * 2).min(MAX_RETRY);
+        self.next_try = Instant::now() + self.delay;
+    }
+
+    pub fn status(&self) -> String {
+        let wait = self.next_try.saturating_duration_since(Instant::now());
+        format!("Reconnecting... attempt {} in {}s", self.attempt, wait.as_secs() + 1)
+    }
+}
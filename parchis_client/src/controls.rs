@@ -0,0 +1,117 @@
+/// Turn hotkeys: while it is our turn and the input line is empty, single keys
+/// are translated into the server commands instead of being typed.
+///
+/// - Space or R rolls the dice.
+/// - 1 to 4 play that pawn, O takes a pawn out of the house.
+/// - Left/Right move the selection between the pawns that can be played and
+///   Enter plays the selected one.
+use crossterm::event::KeyCode;
+use crate::protocol::{GameView, PawnSpot};
+
+pub enum KeyOutcome {
+    /// Send this command to the server.
+    Command(String),
+    /// The key was consumed without sending anything.
+    Handled,
+    /// Not a hotkey right now; treat it as regular input.
+    Ignored,
+}
+
+#[derive(Default)]
+pub struct Controls {
+    /// Our player name, once we have sent a `JOIN`.
+    pub me: Option<String>,
+    /// Number of the selected pawn, always one of the legal pawns.
+    pub selected: Option<usize>,
+}
+
+impl Controls {
+    pub fn new(me: Option<String>) -> Self {
+        Controls { me, selected: None }
+    }
+
+    /// Remembers the name from a `JOIN` typed by hand.
+    pub fn note_sent_command(&mut self, command: &str) {
+        let mut parts = command.split_whitespace();
+        if let (Some("JOIN"), Some(name)) = (parts.next(), parts.next()) {
+            self.me = Some(name.to_string());
+        }
+    }
+
+    pub fn legal_pawns(&self, view: &GameView) -> Vec<usize> {
+        self.me.as_deref().map_or_else(Vec::new, |me| view.legal_pawns(me))
+    }
+
+    /// Drops a selection that no longer points at a legal pawn, e.g. after a new state arrives.
+    pub fn refresh(&mut self, view: &GameView) {
+        let legal = self.legal_pawns(view);
+        if self.selected.is_some_and(|pawn| !legal.contains(&pawn)) {
+            self.selected = None;
+        }
+        if self.selected.is_none() {
+            self.selected = legal.first().copied();
+        }
+    }
+
+    pub fn handle_key(&mut self, code: KeyCode, view: Option<&GameView>) -> KeyOutcome {
+        let (Some(view), Some(me)) = (view, self.me.as_deref()) else {
+            return KeyOutcome::Ignored;
+        };
+        if !view.is_turn_of(me) {
+            return KeyOutcome::Ignored;
+        }
+
+        let legal = view.legal_pawns(me);
+
+        match code {
+            KeyCode::Char(' ' | 'r' | 'R') if view.dice.is_none() => KeyOutcome::Command("ROLL".to_string()),
+            KeyCode::Char(digit @ '1'..='4') if view.dice.is_some() => {
+                let pawn = digit as usize - '0' as usize;
+                if legal.contains(&pawn) {
+                    KeyOutcome::Command(self.command_for(view, me, pawn))
+                } else {
+                    KeyOutcome::Handled
+                }
+            }
+            KeyCode::Char('o' | 'O') if view.dice.is_some() => {
+                let can_leave_house = legal.iter().any(|pawn| self.is_in_house(view, me, *pawn));
+                if can_leave_house {
+                    KeyOutcome::Command("MOVE_OUT".to_string())
+                } else {
+                    KeyOutcome::Handled
+                }
+            }
+            KeyCode::Left | KeyCode::Right if !legal.is_empty() => {
+                let current = self.selected.and_then(|pawn| legal.iter().position(|p| *p == pawn));
+                let next = match (code, current) {
+                    (KeyCode::Left, Some(index)) => (index + legal.len() - 1) % legal.len(),
+                    (KeyCode::Right, Some(index)) => (index + 1) % legal.len(),
+                    _ => 0,
+                };
+                self.selected = Some(legal[next]);
+                KeyOutcome::Handled
+            }
+            KeyCode::Enter => match self.selected.filter(|pawn| legal.contains(pawn)) {
+                Some(pawn) => KeyOutcome::Command(self.command_for(view, me, pawn)),
+                None => KeyOutcome::Ignored,
+            },
+            _ => KeyOutcome::Ignored,
+        }
+    }
+
+    fn is_in_house(&self, view: &GameView, me: &str, pawn: usize) -> bool {
+        view.player(me)
+            .and_then(|player| player.pawns.get(pawn - 1))
+            .is_some_and(|spot| *spot == PawnSpot::House)
+    }
+
+    /// The server takes out whichever house pawn comes first, so any pawn in
+    /// the house maps to `MOVE_OUT`.
+    fn command_for(&self, view: &GameView, me: &str, pawn: usize) -> String {
+        if self.is_in_house(view, me, pawn) {
+            "MOVE_OUT".to_string()
+        } else {
+            format!("MOVE {}", pawn)
+        }
+    }
+}
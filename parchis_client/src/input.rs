@@ -0,0 +1,150 @@
+use std::env;
+use std::fs::{self, OpenOptions};
+use std::io::Write;
+use std::path::PathBuf;
+
+const MAX_HISTORY: usize = 500;
+const HISTORY_FILE: &str = ".parchis_history";
+
+/// The command line at the bottom of the screen: cursor movement, editing and
+/// a command history that is kept in `~/.parchis_history` between sessions.
+#[derive(Default)]
+pub struct LineEditor {
+    buffer: Vec<char>,
+    cursor: usize,
+    history: Vec<String>,
+    /// Position in `history` while browsing with Up/Down.
+    browsing: Option<usize>,
+    /// What was typed before browsing started, restored when going past the newest entry.
+    draft: Vec<char>,
+    history_path: Option<PathBuf>,
+}
+
+impl LineEditor {
+    /// Creates an editor with the history saved by previous sessions.
+    pub fn with_history() -> Self {
+        let history_path = env::var_os("HOME").map(|home| PathBuf::from(home).join(HISTORY_FILE));
+        let mut history: Vec<String> = history_path.as_ref()
+            .and_then(|path| fs::read_to_string(path).ok())
+            .map(|contents| contents.lines().map(str::to_string).collect())
+            .unwrap_or_default();
+        if history.len() > MAX_HISTORY {
+            history.drain(..history.len() - MAX_HISTORY);
+            if let Some(path) = &history_path {
+                let _ = fs::write(path, history.join("\n") + "\n");
+            }
+        }
+
+        LineEditor { history, history_path, ..LineEditor::default() }
+    }
+
+    pub fn text(&self) -> String {
+        self.buffer.iter().collect()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.buffer.is_empty()
+    }
+
+    /// Cursor position in characters from the start of the line.
+    pub fn cursor(&self) -> usize {
+        self.cursor
+    }
+
+    pub fn insert(&mut self, c: char) {
+        self.buffer.insert(self.cursor, c);
+        self.cursor += 1;
+    }
+
+    pub fn backspace(&mut self) {
+        if self.cursor > 0 {
+            self.cursor -= 1;
+            self.buffer.remove(self.cursor);
+        }
+    }
+
+    pub fn delete(&mut self) {
+        if self.cursor < self.buffer.len() {
+            self.buffer.remove(self.cursor);
+        }
+    }
+
+    pub fn move_left(&mut self) {
+        self.cursor = self.cursor.saturating_sub(1);
+    }
+
+    pub fn move_right(&mut self) {
+        self.cursor = (self.cursor + 1).min(self.buffer.len());
+    }
+
+    pub fn move_home(&mut self) {
+        self.cursor = 0;
+    }
+
+    pub fn move_end(&mut self) {
+        self.cursor = self.buffer.len();
+    }
+
+    pub fn history_previous(&mut self) {
+        let index = match self.browsing {
+            Some(0) => return,
+            Some(index) => index - 1,
+            None if self.history.is_empty() => return,
+            None => {
+                self.draft = self.buffer.clone();
+                self.history.len() - 1
+            }
+        };
+        self.browsing = Some(index);
+        self.set_buffer(self.history[index].chars().collect());
+    }
+
+    pub fn history_next(&mut self) {
+        let Some(index) = self.browsing else {
+            return;
+        };
+        if index + 1 < self.history.len() {
+            self.browsing = Some(index + 1);
+            self.set_buffer(self.history[index + 1].chars().collect());
+        } else {
+            self.browsing = None;
+            let draft = std::mem::take(&mut self.draft);
+            self.set_buffer(draft);
+        }
+    }
+
+    /// Clears the line and returns it, recording it in the history.
+    pub fn submit(&mut self) -> String {
+        let line = self.text();
+        self.set_buffer(Vec::new());
+        self.browsing = None;
+        self.draft.clear();
+
+        let trimmed = line.trim();
+        // Commands carrying a password never reach the history file.
+        let secret = trimmed.starts_with("REGISTER ") || trimmed.starts_with("LOGIN ");
+        if !trimmed.is_empty() && !secret && self.history.last().map(String::as_str) != Some(trimmed) {
+            self.history.push(trimmed.to_string());
+            if self.history.len() > MAX_HISTORY {
+                self.history.remove(0);
+            }
+            self.append_to_file(trimmed);
+        }
+        line
+    }
+
+    fn set_buffer(&mut self, buffer: Vec<char>) {
+        self.buffer = buffer;
+        self.cursor = self.buffer.len();
+    }
+
+    /// History is best effort: a read-only home directory just means it isn't kept.
+    fn append_to_file(&self, line: &str) {
+        let Some(path) = &self.history_path else {
+            return;
+        };
+        if let Ok(mut file) = OpenOptions::new().create(true).append(true).open(path) {
+            let _ = writeln!(file, "{}", line);
+        }
+    }
+}
@@ -0,0 +1,230 @@
+/// Hot-seat game run in-process with `--local`: the players share the keyboard
+/// and the game engine from `parchis_core` replaces the server.
+///
+/// The game speaks the same text and `STATE` messages as the server, so the
+/// rest of the client does not care where they come from. Bots take a pawn out
+/// whenever they can and otherwise advance their leading pawn.
+use std::collections::{HashSet, VecDeque};
+use std::time::{Duration, Instant};
+use parchis_core::game_state::{player_id, ClientId, Color, Game, MoveOutcome, RollOutcome};
+use parchis_core::rules::Rules;
+use crate::connection::Incoming;
+
+/// Pause before each bot action so the players can follow what happens.
+const BOT_DELAY: Duration = Duration::from_millis(700);
+
+const SEAT_COLORS: [Color; 4] = [Color::Red, Color::Green, Color::Yellow, Color::Blue];
+
+pub struct LocalGame {
+    game: Game,
+    bots: HashSet<String>,
+    outbox: VecDeque<String>,
+    last_action: Instant,
+}
+
+impl LocalGame {
+    /// Seats the humans and then the bots in color order and starts the game.
+    pub fn new(humans: &[String], bots: usize) -> Result<LocalGame, String> {
+        let rules = Rules::default();
+        let seats = humans.len() + bots;
+        if seats < rules.min_players || seats > rules.max_players {
+            return Err(format!("a local game needs {} to {} players, got {}", rules.min_players, rules.max_players, seats));
+        }
+
+        let mut names: Vec<String> = humans.to_vec();
+        names.extend((1..=bots).map(|n| format!("Bot{}", n)));
+
+        let mut game = Game::new(rules);
+        let mut bot_names = HashSet::new();
+        for (seat, name) in names.iter().enumerate() {
+            let key = player_id(name);
+            // Seats count from 1: `ClientId::UNCLAIMED` is 0.
+            game.add_player(name.clone(), ClientId(seat as u64 + 1)).map_err(|e| format!("cannot seat '{}': {}", name, e))?;
+            game.set_player_color(&key, SEAT_COLORS[seat].clone());
+            if let Some(player) = game.get_player_mut(&key) {
+                player.mark_as_fully_registered();
+            }
+            if seat >= humans.len() {
+                bot_names.insert(key);
+            }
+        }
+        game.start_game();
+
+        let mut local = LocalGame {
+            game,
+            bots: bot_names,
+            outbox: VecDeque::new(),
+            last_action: Instant::now(),
+        };
+        local.push(&format!("Game started!\n{}", local.game.get_turn_order_message()));
+        local.announce_turn();
+        local.push(&local.game.get_state_line());
+        Ok(local)
+    }
+
+    /// The human whose turn it is, i.e. who the hotkeys currently play for.
+    pub fn current_human(&self) -> Option<String> {
+        self.game.get_current_turn()
+            .filter(|name| !self.bots.contains(*name))
+            .cloned()
+    }
+
+    /// Plays a command typed at the keyboard for whoever's turn it is.
+    pub fn send(&mut self, line: &str) {
+        let Some(player) = self.game.get_current_turn().cloned() else {
+            return;
+        };
+        if !self.game.is_in_progress() {
+            self.push("The game is over. Press Esc to quit.");
+        } else if self.bots.contains(&player) {
+            self.push(&format!("Wait for {} to play.", player));
+        } else {
+            self.play(&player, line);
+        }
+    }
+
+    /// Returns the next message to show, letting a bot act when its turn is due.
+    pub fn poll(&mut self) -> Incoming {
+        if self.outbox.is_empty() && self.last_action.elapsed() >= BOT_DELAY {
+            if let Some(bot) = self.game.get_current_turn().filter(|name| self.bots.contains(*name)).cloned() {
+                if self.game.is_in_progress() {
+                    let command = self.bot_command(&bot);
+                    self.play(&bot, &command);
+                }
+            }
+        }
+
+        match self.outbox.pop_front() {
+            Some(message) => Incoming::Message(message),
+            None => Incoming::Empty,
+        }
+    }
+
+    fn play(&mut self, player: &str, line: &str) {
+        self.last_action = Instant::now();
+        let parts: Vec<&str> = line.split_whitespace().collect();
+
+        let result = match parts.as_slice() {
+            ["ROLL"] => self.roll(player),
+            ["MOVE_OUT"] => self.game.move_pawn_out(player).map(|()| {
+                self.push(&format!("{} moved a pawn out of the house.", player));
+                self.announce_turn();
+            }),
+            ["MOVE", pawn] => match pawn.parse::<usize>() {
+                Ok(pawn) => self.game.move_pawn(player, pawn).map(|moved| {
+                    self.push(&format!("{} moved pawn {}.", player, pawn));
+                    self.announce_move_result(player, &moved);
+                }),
+                Err(_) => {
+                    self.push("Invalid pawn number format.");
+                    Ok(())
+                }
+            },
+            _ => {
+                self.push("Unknown or invalid command. In a local game use ROLL, MOVE <pawn number> or MOVE_OUT.");
+                Ok(())
+            }
+        };
+
+        if let Err(e) = result {
+            self.push(&e.to_string());
+        }
+        self.push(&self.game.get_state_line());
+    }
+
+    fn roll(&mut self, player: &str) -> Result<(), parchis_core::game_state::TurnError> {
+        let (dice_value, outcome) = self.game.roll(player)?;
+        self.push(&format!("{} rolled a {}.", player, dice_value));
+
+        match outcome {
+            RollOutcome::MustMoveOut => self.push("A pawn can leave the house. Press O or type 'MOVE_OUT'."),
+            RollOutcome::MoveOrMoveOut => self.push("Take a pawn out with O or move a pawn with its number."),
+            RollOutcome::ChoosePawn => self.push("Choose a pawn to move with its number."),
+            RollOutcome::NoMove => {
+                self.push(&format!("A {} is needed to move a pawn out of the house.", self.game.rules().exit_roll));
+                self.announce_turn();
+            }
+            RollOutcome::AutoMoved(moved) => {
+                if moved.reached_goal {
+                    self.push(&format!("{}'s pawn {} reached the goal!", player, moved.pawn));
+                } else {
+                    self.push(&format!("{}'s pawn {} has been moved.", player, moved.pawn));
+                }
+                self.announce_move_result(player, &moved);
+            }
+        }
+        Ok(())
+    }
+
+    fn announce_move_result(&mut self, player: &str, moved: &MoveOutcome) {
+        if moved.game_over {
+            let winner = self.game.standings().into_iter().next().unwrap_or_else(|| player.to_string());
+            self.push(&format!("{} has won the game!", winner));
+            let standings = self.game.get_standings_message();
+            self.push(standings.trim_end());
+            return;
+        }
+        if let Some(place) = moved.place {
+            self.push(&format!("{} brought every pawn home and finished in place {}!", player, place));
+        }
+        self.announce_turn();
+    }
+
+    fn bot_command(&self, bot: &str) -> String {
+        let Some(dice) = self.game.pending_roll() else {
+            return "ROLL".to_string();
+        };
+        let Some(player) = self.game.get_player(bot) else {
+            return "ROLL".to_string();
+        };
+
+        let (in_house, _) = player.get_pawn_counts();
+        if in_house > 0 && dice == self.game.rules().exit_roll {
+            return "MOVE_OUT".to_string();
+        }
+        match player.pawns_on_board().iter().max_by_key(|(_, position)| *position) {
+            Some((pawn, _)) => format!("MOVE {}", pawn),
+            None => "MOVE_OUT".to_string(),
+        }
+    }
+
+    fn announce_turn(&mut self) {
+        if let Some(player) = self.game.get_current_turn().cloned() {
+            self.push(&format!("It's {}'s turn to roll the dice.", player));
+        }
+    }
+
+    fn push(&mut self, message: &str) {
+        self.outbox.push_back(message.to_string());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use parchis_core::dice::ScriptedDice;
+    use super::*;
+
+    fn drain(local: &mut LocalGame) -> Vec<String> {
+        std::iter::from_fn(|| match local.poll() {
+            Incoming::Message(message) => Some(message),
+            _ => None,
+        }).collect()
+    }
+
+    #[test]
+    fn every_seat_can_roll() {
+        let mut local = LocalGame::new(&["ana".to_string(), "bob".to_string()], 0).unwrap();
+        // A 1 never lets a pawn out, so every roll passes the turn.
+        local.game.set_dice(Box::new(ScriptedDice::new(vec![1])));
+        drain(&mut local);
+
+        let mut messages = Vec::new();
+        for _ in 0..4 {
+            local.send("ROLL");
+            messages.extend(drain(&mut local));
+        }
+
+        assert_eq!(messages.iter().filter(|message| message.starts_with("ana rolled a 1")).count(), 2);
+        assert_eq!(messages.iter().filter(|message| message.starts_with("bob rolled a 1")).count(), 2);
+    }
+}
@@ -1,70 +1,346 @@
-use std::io::{self, Write, BufRead, BufReader};
-use std::net::TcpStream;
-use std::thread;
-use std::sync::mpsc::{self, Receiver, TryRecvError};
-use crossterm::event::{self, Event, KeyCode};
+mod board;
+mod config;
+mod connection;
+mod controls;
+mod input;
+mod local;
+mod protocol;
+mod replay;
+mod ui;
+
+use std::io;
+use std::path::Path;
+use crossterm::event::{self, Event, KeyCode, KeyModifiers};
+use parchis_core::dice::{random_entropy, FairDiceCheck};
+use parchis_core::events::{GameEvent, Replay};
+use parchis_core::game_state::player_id;
+use config::{Config, USAGE};
+use connection::{Connection, Incoming, Reconnect};
+use controls::{Controls, KeyOutcome};
+use local::LocalGame;
+use protocol::{parse_message, GameStatus, ServerMessage};
+use replay::ReplayViewer;
+use ui::{TerminalGuard, Ui};
 
 fn main() -> std::io::Result<()> {
+    let config = match Config::from_args(std::env::args().skip(1)) {
+        Ok(config) => config,
+        Err(e) => {
+            eprintln!("{}\n{}", e, USAGE);
+            std::process::exit(2);
+        }
+    };
 
-    let mut stream = TcpStream::connect("127.0.0.1:7878")?;
-    println!("Successfully connected to the server at 127.0.0.1:7878");
+    if let Some(path) = &config.replay {
+        return run_replay(path);
+    }
+    if let Some(path) = &config.verify {
+        verify_replay(path);
+    }
+
+    let mut link = if config.local {
+        match LocalGame::new(&config.players, config.bots) {
+            Ok(game) => Some(Link::Local(Box::new(game))),
+            Err(e) => {
+                eprintln!("{}\n{}", e, USAGE);
+                std::process::exit(2);
+            }
+        }
+    } else {
+        Some(Link::Online(Connection::open(&config.address())?))
+    };
+    let mut reconnect: Option<Reconnect> = None;
+    let mut session_token: Option<String> = None;
+    // Set while a `RESUME` sent after a reconnect is waiting for its answer.
+    let mut resuming = false;
+    // Fair dice events seen since the commitment, checked when the secret is revealed.
+    let mut dice_check: Option<FairDiceCheck> = None;
+    // Set from the commitment until our entropy or the first roll shows up.
+    let mut entropy_owed = false;
 
-    let (tx, rx): (mpsc::Sender<String>, Receiver<String>) = mpsc::channel();
-    let mut reader = BufReader::new(stream.try_clone()?);
+    if let (Some(Link::Online(connection)), Some(name)) = (link.as_mut(), &config.name) {
+        connection.send(&format!("JOIN {}", name))?;
+    }
 
-    thread::spawn(move || {
-        let mut message_accumulator = String::new();
+    let _terminal = TerminalGuard::enter()?;
+    let mut stdout = io::stdout();
+    let mut ui = Ui::new();
+    let mut controls = Controls::new(config.name.clone());
+    if config.local {
+        ui.push_message("Local game: players take turns at this keyboard. Press Esc to quit.");
+    } else {
+        ui.push_message(&format!("Connected to the server at {}. Press Esc to quit.", config.address()));
+    }
+    ui.draw(&mut stdout, &controls)?;
 
-        loop {
-            let mut response = String::new();
-            match reader.read_line(&mut response) {
-                Ok(_) => {
-                    message_accumulator.push_str(&response);
+    loop {
+        let mut redraw = false;
+        let mut replies: Vec<String> = Vec::new();
 
-                    if message_accumulator.ends_with("END_OF_MESSAGE\n") {
-                        let clean_message = message_accumulator
-                            .replace("END_OF_MESSAGE\n", "");
-                        tx.send(clean_message).expect("Failed to send response to main thread");
-                        message_accumulator.clear();
+        if let Some(current) = &mut link {
+            loop {
+                match current.poll() {
+                    Incoming::Message(message) => {
+                        match parse_message(&message) {
+                            ServerMessage::State(view) => {
+                                // In a local game the hotkeys play for whoever's turn it is.
+                                if let Link::Local(game) = current {
+                                    controls.me = game.current_human();
+                                }
+                                // A finished game's seat cannot be resumed; a rematch hands out a new token.
+                                if view.status == GameStatus::Over {
+                                    session_token = None;
+                                    entropy_owed = false;
+                                }
+                                controls.refresh(&view);
+                                ui.set_view(view);
+                            }
+                            ServerMessage::Token(token, name) => {
+                                session_token = Some(token);
+                                resuming = false;
+                                // A bare `JOIN` after `LOGIN`, or a `RESUME`, only learns the name here.
+                                if name.is_some() {
+                                    controls.me = name;
+                                }
+                            }
+                            ServerMessage::ResumeRefused(text) => {
+                                ui.push_message(&text);
+                                session_token = None;
+                                if std::mem::take(&mut resuming) {
+                                    match &controls.me {
+                                        Some(name) => replies.push(format!("JOIN {}", name)),
+                                        None => ui.push_message("Your seat is gone. Type 'JOIN <name>' to play again."),
+                                    }
+                                }
+                            }
+                            ServerMessage::Dice(event) => {
+                                match &event {
+                                    GameEvent::Committed { .. } => {
+                                        dice_check = Some(FairDiceCheck::new());
+                                        entropy_owed = true;
+                                    }
+                                    GameEvent::Entropy { player, .. } if controls.me.as_ref().is_some_and(|me| player_id(me) == *player) => {
+                                        entropy_owed = false;
+                                    }
+                                    GameEvent::Rolled { .. } => entropy_owed = false,
+                                    _ => {}
+                                }
+                                if let Some(check) = &mut dice_check {
+                                    check.observe(&event);
+                                }
+                                if let GameEvent::Revealed { .. } = event {
+                                    ui.push_message(&match dice_check.take().map(|check| check.verify()) {
+                                        Some(Ok(rolls)) => format!("Fair dice checked: all {} rolls match the server's commitment.", rolls),
+                                        Some(Err(e)) => format!("Fair dice check FAILED: {}.", e),
+                                        None => "The server revealed its dice secret, but this client did not see the game from the start to check it.".to_string(),
+                                    });
+                                }
+                            }
+                            ServerMessage::Text(text) => ui.push_message(&text),
+                        }
+                        redraw = true;
+                    }
+                    Incoming::Empty => break,
+                    Incoming::Closed => {
+                        link = None;
+                        dice_check = None;
+                        reconnect = Some(Reconnect::new());
+                        ui.push_message("Connection to the server lost.");
+                        redraw = true;
+                        break;
                     }
                 }
-                Err(e) => {
-                    eprintln!("Failed to read from server: {}", e);
-                    break;
+            }
+        }
+
+        // Spectators never hold a seat, so they never send any.
+        if entropy_owed && controls.me.is_some() && link.is_some() {
+            entropy_owed = false;
+            replies.push(format!("ENTROPY {}", random_entropy()));
+        }
+        for reply in replies {
+            send_line(&mut link, &mut reconnect, &mut ui, &reply);
+        }
+
+        if let Some(retry) = &mut reconnect {
+            if retry.is_due() {
+                match Connection::open(&config.address()) {
+                    Ok(connection) => {
+                        link = Some(Link::Online(connection));
+                        reconnect = None;
+                        ui.push_message("Reconnected.");
+                        // Take our seat back if we had one, otherwise join again by name.
+                        let rejoin = session_token.as_ref().map(|token| format!("RESUME {}", token))
+                            .or_else(|| controls.me.as_ref().map(|name| format!("JOIN {}", name)));
+                        if let Some(command) = rejoin {
+                            resuming = command.starts_with("RESUME");
+                            send_line(&mut link, &mut reconnect, &mut ui, &command);
+                        }
+                    }
+                    Err(_) => retry.failed(),
                 }
             }
         }
-    });
 
-    let mut input = String::new();
-    loop {
-        match rx.try_recv() {
-            Ok(message) => print!("{}", message),
-            Err(TryRecvError::Empty) => {},
-            Err(TryRecvError::Disconnected) => break,
+        let status = reconnect.as_ref().map(Reconnect::status);
+        if status != ui.status {
+            ui.status = status;
+            redraw = true;
         }
 
         if event::poll(std::time::Duration::from_millis(100))? {
-            if let Event::Key(key_event) = event::read()? {
-                match key_event.code {
-                    KeyCode::Char(c) => {
-                        input.push(c);
-                        print!("{}", c);
-                        io::stdout().flush()?;
-                    }
-                    KeyCode::Enter => {
-                        println!(); 
-                        stream.write_all(input.as_bytes())?;
-                        input.clear();
-                    }
-                    KeyCode::Backspace => {
-                        input.pop();
+            match event::read()? {
+                Event::Key(key_event) => {
+                    let outcome = if ui.input.is_empty() {
+                        controls.handle_key(key_event.code, ui.view())
+                    } else {
+                        KeyOutcome::Ignored
+                    };
+
+                    match outcome {
+                        KeyOutcome::Command(command) => {
+                            ui.push_message(&format!("> {}", command));
+                            send_line(&mut link, &mut reconnect, &mut ui, &command);
+                        }
+                        KeyOutcome::Handled => {}
+                        KeyOutcome::Ignored => match key_event.code {
+                            KeyCode::Char('c') if key_event.modifiers.contains(KeyModifiers::CONTROL) => break,
+                            KeyCode::Esc => break,
+                            KeyCode::Char(c) => ui.input.insert(c),
+                            KeyCode::Enter => {
+                                let line = ui.input.submit();
+                                controls.note_sent_command(&line);
+                                if line.split_whitespace().next() == Some("LEAVE") {
+                                    session_token = None;
+                                }
+                                send_line(&mut link, &mut reconnect, &mut ui, &line);
+                            }
+                            KeyCode::Backspace => ui.input.backspace(),
+                            KeyCode::Delete => ui.input.delete(),
+                            KeyCode::Left => ui.input.move_left(),
+                            KeyCode::Right => ui.input.move_right(),
+                            KeyCode::Home => ui.input.move_home(),
+                            KeyCode::End => ui.input.move_end(),
+                            KeyCode::Up => ui.input.history_previous(),
+                            KeyCode::Down => ui.input.history_next(),
+                            KeyCode::PageUp => ui.scroll_up(5),
+                            KeyCode::PageDown => ui.scroll_down(5),
+                            _ => {}
+                        },
                     }
-                    _ => {} 
+                    redraw = true;
                 }
+                Event::Resize(_, _) => redraw = true,
+                _ => {}
             }
         }
+
+        if redraw {
+            ui.draw(&mut stdout, &controls)?;
+        }
     }
 
     Ok(())
 }
+
+/// Shows a replay file on the board: Left/Right step through it, Home/End jump to either end.
+fn run_replay(path: &Path) -> io::Result<()> {
+    let mut viewer = match ReplayViewer::load(path) {
+        Ok(viewer) => viewer,
+        Err(e) => {
+            eprintln!("Could not load the replay {}: {}", path.display(), e);
+            std::process::exit(2);
+        }
+    };
+
+    let _terminal = TerminalGuard::enter()?;
+    let mut stdout = io::stdout();
+    let mut ui = Ui::new();
+    let controls = Controls::new(None);
+    ui.push_message(&format!("Replaying {}.", path.display()));
+    ui.push_message("Right or Space: next event, Left: previous event, Home/End: first/last, Esc: quit.");
+
+    loop {
+        ui.set_view(viewer.view().clone());
+        ui.status = Some(viewer.status());
+        ui.draw(&mut stdout, &controls)?;
+
+        // Any other event, such as a resize, just redraws.
+        let Event::Key(key_event) = event::read()? else {
+            continue;
+        };
+        match key_event.code {
+            KeyCode::Char('c') if key_event.modifiers.contains(KeyModifiers::CONTROL) => break,
+            KeyCode::Esc | KeyCode::Char('q') => break,
+            KeyCode::Right | KeyCode::Char(' ') => viewer.forward(),
+            KeyCode::Left | KeyCode::Backspace => viewer.back(),
+            KeyCode::Home => viewer.first(),
+            KeyCode::End => viewer.last(),
+            _ => {}
+        }
+    }
+
+    Ok(())
+}
+
+/// Checks the fair dice of a replay file, prints the result and exits.
+fn verify_replay(path: &Path) -> ! {
+    let replay = match Replay::load(path) {
+        Ok(replay) => replay,
+        Err(e) => {
+            eprintln!("Could not load the replay {}: {}", path.display(), e);
+            std::process::exit(2);
+        }
+    };
+
+    let mut check = FairDiceCheck::new();
+    for event in &replay.events {
+        check.observe(event);
+    }
+    match check.verify() {
+        Ok(rolls) => {
+            println!("{}: all {} rolls match the committed dice.", path.display(), rolls);
+            std::process::exit(0);
+        }
+        Err(e) => {
+            println!("{}: the dice do not check out: {}.", path.display(), e);
+            std::process::exit(1);
+        }
+    }
+}
+
+/// Where commands go and messages come from: a server, or a game on this keyboard.
+enum Link {
+    Online(Connection),
+    Local(Box<LocalGame>),
+}
+
+impl Link {
+    fn poll(&mut self) -> Incoming {
+        match self {
+            Link::Online(connection) => connection.poll(),
+            Link::Local(game) => game.poll(),
+        }
+    }
+}
+
+/// Sends a command, switching to reconnect mode if the connection turns out to be gone.
+fn send_line(link: &mut Option<Link>, reconnect: &mut Option<Reconnect>, ui: &mut Ui, line: &str) {
+    let connection = match link {
+        Some(Link::Online(connection)) => connection,
+        Some(Link::Local(game)) => {
+            game.send(line);
+            return;
+        }
+        None => {
+            ui.push_message("Not connected to the server; the command was not sent.");
+            return;
+        }
+    };
+
+    if connection.send(line).is_err() {
+        *link = None;
+        *reconnect = Some(Reconnect::new());
+        ui.push_message("Connection to the server lost.");
+    }
+}
@@ -0,0 +1,192 @@
+/// Client-side view of the messages sent by the server.
+///
+/// Most messages are free text for the log, but the server also sends a
+/// `STATE` line whenever the board changes, which is parsed into a `GameView`,
+/// and `DICE <event>` lines for games with fair dice.
+use parchis_core::events::GameEvent;
+use parchis_core::game_state::player_id;
+
+/// The server's answer to a `RESUME` whose seat is gone.
+const RESUME_REFUSED: &str = "Unknown or expired session token.";
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum PawnColor {
+    Red,
+    Green,
+    Blue,
+    Yellow,
+    Unassigned,
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum PawnSpot {
+    House,
+    Board(u8),
+    Goal,
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum GameStatus {
+    Waiting,
+    Playing,
+    Over,
+}
+
+#[derive(Clone, Debug)]
+pub struct PlayerView {
+    pub name: String,
+    pub color: PawnColor,
+    pub pawns: Vec<PawnSpot>,
+}
+
+#[derive(Clone, Debug)]
+pub struct GameView {
+    pub status: GameStatus,
+    pub turn: Option<String>,
+    /// The current player's roll while it still has to be played.
+    pub dice: Option<u8>,
+    /// The roll needed to take a pawn out of the house.
+    pub exit_roll: u8,
+    pub players: Vec<PlayerView>,
+}
+
+impl GameView {
+    /// Looks up a player by name; the `STATE` line names players by their `player_id`.
+    pub fn player(&self, name: &str) -> Option<&PlayerView> {
+        self.players.iter().find(|player| player.name == player_id(name))
+    }
+
+    pub fn is_turn_of(&self, name: &str) -> bool {
+        self.status == GameStatus::Playing
+            && self.turn.as_deref().is_some_and(|turn| turn == player_id(name))
+    }
+
+    /// Numbers of the pawns `name` can play with the pending roll, in order.
+    pub fn legal_pawns(&self, name: &str) -> Vec<usize> {
+        let (Some(dice), Some(player)) = (self.dice, self.player(name)) else {
+            return Vec::new();
+        };
+        if !self.is_turn_of(name) {
+            return Vec::new();
+        }
+
+        player.pawns.iter().enumerate()
+            .filter(|(_, spot)| match spot {
+                PawnSpot::Board(_) => true,
+                PawnSpot::House => dice == self.exit_roll,
+                PawnSpot::Goal => false,
+            })
+            .map(|(index, _)| index + 1)
+            .collect()
+    }
+}
+
+pub enum ServerMessage {
+    State(GameView),
+    /// Session token to send with `RESUME` after a reconnect, and the name of
+    /// the seat it is for.
+    Token(String, Option<String>),
+    /// A fair dice event: the commitment, entropy, a roll or the revealed secret.
+    Dice(GameEvent),
+    /// `RESUME` was refused; the text is still shown.
+    ResumeRefused(String),
+    Text(String),
+}
+
+pub fn parse_message(message: &str) -> ServerMessage {
+    if let Some(token) = message.trim().strip_prefix("TOKEN ") {
+        return match token.split_once(' ') {
+            Some((token, name)) => ServerMessage::Token(token.to_string(), Some(name.to_string())),
+            None => ServerMessage::Token(token.to_string(), None),
+        };
+    }
+    if let Some(event) = message.trim().strip_prefix("DICE ").and_then(GameEvent::parse) {
+        return ServerMessage::Dice(event);
+    }
+    if message.trim().starts_with(RESUME_REFUSED) {
+        return ServerMessage::ResumeRefused(message.to_string());
+    }
+
+    match message.trim().strip_prefix("STATE ") {
+        Some(state) => match parse_state(state) {
+            Some(view) => ServerMessage::State(view),
+            None => ServerMessage::Text(message.to_string()),
+        },
+        None => ServerMessage::Text(message.to_string()),
+    }
+}
+
+fn parse_state(state: &str) -> Option<GameView> {
+    let mut view = GameView {
+        status: GameStatus::Waiting,
+        turn: None,
+        dice: None,
+        exit_roll: 6,
+        players: Vec::new(),
+    };
+
+    for field in state.split_whitespace() {
+        let (key, value) = field.split_once('=')?;
+        match key {
+            "status" => {
+                view.status = match value {
+                    "waiting" => GameStatus::Waiting,
+                    "playing" => GameStatus::Playing,
+                    "over" => GameStatus::Over,
+                    _ => return None,
+                }
+            }
+            "turn" => view.turn = (value != "-").then(|| value.to_string()),
+            "dice" => view.dice = value.parse().ok(),
+            "exit" => view.exit_roll = value.parse().ok()?,
+            "players" => {
+                view.players = value.split(';')
+                    .filter(|player| !player.is_empty())
+                    .map(parse_player)
+                    .collect::<Option<Vec<_>>>()?;
+            }
+            _ => {}
+        }
+    }
+
+    Some(view)
+}
+
+fn parse_player(player: &str) -> Option<PlayerView> {
+    let mut parts = player.rsplitn(3, ':');
+    let pawns = parts.next()?;
+    let color = parts.next()?;
+    let name = parts.next()?;
+
+    let color = match color {
+        "Red" => PawnColor::Red,
+        "Green" => PawnColor::Green,
+        "Blue" => PawnColor::Blue,
+        "Yellow" => PawnColor::Yellow,
+        _ => PawnColor::Unassigned,
+    };
+    let pawns = pawns.split(',')
+        .map(|pawn| match pawn {
+            "H" => Some(PawnSpot::House),
+            "G" => Some(PawnSpot::Goal),
+            position => position.parse().ok().map(PawnSpot::Board),
+        })
+        .collect::<Option<Vec<_>>>()?;
+
+    Some(PlayerView { name: name.to_string(), color, pawns })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn names_match_whatever_their_case() {
+        let ServerMessage::State(view) = parse_message("STATE status=playing turn=begoña dice=6 exit=6 players=begoña:Red:H,H,H,H;bob:Blue:H,H,H,H") else {
+            panic!("not a STATE line");
+        };
+        assert!(view.is_turn_of("BEGOÑA"));
+        assert!(view.player("Begoña").is_some());
+        assert!(!view.is_turn_of("Bob"));
+    }
+}
@@ -0,0 +1,114 @@
+/// `--replay <file>`: steps through a finished game on the board view.
+///
+/// The event log is turned into one `GameView` per event up front, so moving
+/// backwards is as cheap as moving forwards.
+use std::io;
+use std::path::Path;
+use parchis_core::events::{GameEvent, Replay};
+use crate::protocol::{GameStatus, GameView, PawnColor, PawnSpot, PlayerView};
+
+pub struct ReplayViewer {
+    /// The board after each event, with the event that produced it.
+    frames: Vec<(String, GameView)>,
+    position: usize,
+}
+
+impl ReplayViewer {
+    pub fn load(path: &Path) -> io::Result<ReplayViewer> {
+        let replay = Replay::load(path)?;
+        let mut view = GameView {
+            status: GameStatus::Waiting,
+            turn: None,
+            dice: None,
+            exit_roll: replay.exit_roll,
+            players: Vec::new(),
+        };
+
+        let mut frames = vec![("Start of the replay".to_string(), view.clone())];
+        for event in &replay.events {
+            apply(&mut view, event, replay.pawns_per_player);
+            frames.push((event.to_string(), view.clone()));
+        }
+        Ok(ReplayViewer { frames, position: 0 })
+    }
+
+    pub fn view(&self) -> &GameView {
+        &self.frames[self.position].1
+    }
+
+    pub fn forward(&mut self) {
+        self.position = (self.position + 1).min(self.frames.len() - 1);
+    }
+
+    pub fn back(&mut self) {
+        self.position = self.position.saturating_sub(1);
+    }
+
+    pub fn first(&mut self) {
+        self.position = 0;
+    }
+
+    pub fn last(&mut self) {
+        self.position = self.frames.len() - 1;
+    }
+
+    /// Where we are in the replay, for the status line.
+    pub fn status(&self) -> String {
+        format!("Replay {}/{}: {}", self.position, self.frames.len() - 1, self.frames[self.position].0)
+    }
+}
+
+fn apply(view: &mut GameView, event: &GameEvent, pawns_per_player: usize) {
+    match event {
+        GameEvent::Joined { player } => view.players.push(PlayerView {
+            name: player.clone(),
+            color: PawnColor::Unassigned,
+            pawns: vec![PawnSpot::House; pawns_per_player],
+        }),
+        GameEvent::Left { player } => view.players.retain(|seat| seat.name != *player),
+        GameEvent::ColorChosen { player, color } => {
+            if let Some(seat) = seat(view, player) {
+                seat.color = match color.as_str() {
+                    "Red" => PawnColor::Red,
+                    "Green" => PawnColor::Green,
+                    "Blue" => PawnColor::Blue,
+                    "Yellow" => PawnColor::Yellow,
+                    _ => PawnColor::Unassigned,
+                };
+            }
+        }
+        GameEvent::Started { first } => {
+            view.status = GameStatus::Playing;
+            view.turn = Some(first.clone());
+        }
+        GameEvent::Turn { player } => {
+            view.turn = Some(player.clone());
+            view.dice = None;
+        }
+        GameEvent::Rolled { value, .. } => view.dice = Some(*value),
+        GameEvent::MovedOut { player, pawn } => set_pawn(view, player, *pawn, PawnSpot::Board(1)),
+        GameEvent::Moved { player, pawn, to } => {
+            let spot = match to.as_str() {
+                "G" => PawnSpot::Goal,
+                "H" => PawnSpot::House,
+                position => position.parse().map_or(PawnSpot::House, PawnSpot::Board),
+            };
+            set_pawn(view, player, *pawn, spot);
+        }
+        GameEvent::Committed { .. } | GameEvent::Entropy { .. } | GameEvent::Finished { .. } | GameEvent::Revealed { .. } => {}
+        GameEvent::Won { .. } => {
+            view.status = GameStatus::Over;
+            view.dice = None;
+        }
+    }
+}
+
+fn seat<'a>(view: &'a mut GameView, player: &str) -> Option<&'a mut PlayerView> {
+    view.players.iter_mut().find(|seat| seat.name == player)
+}
+
+fn set_pawn(view: &mut GameView, player: &str, pawn: usize, spot: PawnSpot) {
+    if let Some(slot) = seat(view, player).and_then(|seat| seat.pawns.get_mut(pawn.wrapping_sub(1))) {
+        *slot = spot;
+    }
+}
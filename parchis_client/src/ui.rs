@@ -0,0 +1,276 @@
+use std::io::{self, Write};
+use crossterm::cursor::{Hide, MoveTo, Show};
+use crossterm::style::{Attribute, Color, Print, ResetColor, SetAttribute, SetBackgroundColor, SetForegroundColor};
+use crossterm::terminal::{self, Clear, ClearType, EnterAlternateScreen, LeaveAlternateScreen};
+use crossterm::{execute, queue};
+use crate::board::{self, Cell, BOARD_SIZE};
+use crate::controls::Controls;
+use crate::input::LineEditor;
+use crate::protocol::{GameStatus, GameView, PawnColor, PawnSpot};
+
+/// Width of one board cell in terminal columns.
+const CELL_WIDTH: u16 = 3;
+const BOARD_WIDTH: u16 = BOARD_SIZE as u16 * CELL_WIDTH;
+const PANEL_X: u16 = BOARD_WIDTH + 3;
+const MAX_LOG_LINES: usize = 500;
+
+/// Puts the terminal in raw mode on the alternate screen and restores it when dropped.
+pub struct TerminalGuard;
+
+impl TerminalGuard {
+    pub fn enter() -> io::Result<TerminalGuard> {
+        terminal::enable_raw_mode()?;
+        execute!(io::stdout(), EnterAlternateScreen, Hide)?;
+        Ok(TerminalGuard)
+    }
+}
+
+impl Drop for TerminalGuard {
+    fn drop(&mut self) {
+        let _ = execute!(io::stdout(), ResetColor, Show, LeaveAlternateScreen);
+        let _ = terminal::disable_raw_mode();
+    }
+}
+
+#[derive(Default)]
+pub struct Ui {
+    log: Vec<String>,
+    /// How many lines the log is scrolled up from the newest message.
+    scroll: usize,
+    view: Option<GameView>,
+    pub input: LineEditor,
+    /// Connection problems, shown at the top of the side panel.
+    pub status: Option<String>,
+}
+
+impl Ui {
+    pub fn new() -> Self {
+        Ui {
+            input: LineEditor::with_history(),
+            ..Ui::default()
+        }
+    }
+
+    pub fn push_message(&mut self, message: &str) {
+        self.log.extend(message.lines().map(str::to_string));
+        if self.log.len() > MAX_LOG_LINES {
+            self.log.drain(..self.log.len() - MAX_LOG_LINES);
+        }
+    }
+
+    pub fn set_view(&mut self, view: GameView) {
+        self.view = Some(view);
+    }
+
+    pub fn view(&self) -> Option<&GameView> {
+        self.view.as_ref()
+    }
+
+    pub fn scroll_up(&mut self, lines: usize) {
+        self.scroll = (self.scroll + lines).min(self.log.len().saturating_sub(1));
+    }
+
+    pub fn scroll_down(&mut self, lines: usize) {
+        self.scroll = self.scroll.saturating_sub(lines);
+    }
+
+    pub fn draw(&self, out: &mut impl Write, controls: &Controls) -> io::Result<()> {
+        let (width, height) = terminal::size()?;
+        queue!(out, Hide, ResetColor, Clear(ClearType::All))?;
+
+        self.draw_board(out, controls)?;
+        self.draw_panel(out, controls)?;
+
+        let log_top = BOARD_SIZE as u16 + 2;
+        let log_height = height.saturating_sub(log_top + 2);
+        self.draw_log(out, log_top, log_height, width)?;
+
+        self.draw_input(out, height.saturating_sub(1), width)?;
+        out.flush()
+    }
+
+    /// Draws the prompt and leaves the terminal cursor where the next character goes,
+    /// scrolling the line horizontally when it is wider than the screen.
+    fn draw_input(&self, out: &mut impl Write, row: u16, width: u16) -> io::Result<()> {
+        const PROMPT: &str = "> ";
+        let room = (width as usize).saturating_sub(PROMPT.len() + 1).max(1);
+        let text: Vec<char> = self.input.text().chars().collect();
+        let start = self.input.cursor().saturating_sub(room);
+        let visible: String = text.iter().skip(start).take(room).collect();
+
+        let cursor_col = (PROMPT.len() + self.input.cursor() - start) as u16;
+        queue!(out, MoveTo(0, row), ResetColor, Print(PROMPT), Print(visible), MoveTo(cursor_col, row), Show)
+    }
+
+    fn draw_board(&self, out: &mut impl Write, controls: &Controls) -> io::Result<()> {
+        let grid = board::layout();
+        let pawns = self.pawns_by_cell();
+
+        // Our pawns that can be played are underlined and the selected one is inverted.
+        let my_color = self.view.as_ref()
+            .zip(controls.me.as_deref())
+            .and_then(|(view, me)| view.player(me))
+            .map(|player| player.color);
+        let legal = self.view.as_ref().map_or_else(Vec::new, |view| controls.legal_pawns(view));
+
+        for (row, cells) in grid.iter().enumerate() {
+            for (col, cell) in cells.iter().enumerate() {
+                queue!(out, MoveTo(col as u16 * CELL_WIDTH, row as u16 + 1))?;
+
+                let background = match cell {
+                    Cell::Blank => Color::Black,
+                    Cell::Track => Color::Grey,
+                    Cell::Start(color) | Cell::HomeColumn(color) => bright(*color),
+                    Cell::House(color) => dark(*color),
+                    Cell::Goal => Color::White,
+                };
+
+                let occupants: Vec<&(PawnColor, usize)> = pawns.iter()
+                    .filter(|(cell, _)| *cell == (row, col))
+                    .map(|(_, pawn)| pawn)
+                    .collect();
+
+                match occupants.as_slice() {
+                    [] if *cell == Cell::Goal => {
+                        queue!(out, SetBackgroundColor(background), SetForegroundColor(Color::Black), Print(" * "))?;
+                    }
+                    [] => queue!(out, SetBackgroundColor(background), Print("   "))?,
+                    [(color, number)] if *cell != Cell::Goal => {
+                        let mine = Some(*color) == my_color;
+                        if mine && controls.selected == Some(*number) && legal.contains(number) {
+                            queue!(out, SetAttribute(Attribute::Reverse))?;
+                        } else if mine && legal.contains(number) {
+                            queue!(out, SetAttribute(Attribute::Underlined))?;
+                        }
+                        queue!(out,
+                            SetBackgroundColor(bright(*color)), SetForegroundColor(Color::Black),
+                            SetAttribute(Attribute::Bold), Print(format!(" {} ", number)), SetAttribute(Attribute::Reset))?;
+                    }
+                    [(color, _), ..] => {
+                        let label = format!("{:^3}", format!("{}x", occupants.len()));
+                        queue!(out,
+                            SetBackgroundColor(bright(*color)), SetForegroundColor(Color::Black),
+                            SetAttribute(Attribute::Bold), Print(label), SetAttribute(Attribute::Reset))?;
+                    }
+                }
+            }
+        }
+
+        queue!(out, ResetColor)
+    }
+
+    /// Every pawn on screen as `(cell, (color, pawn number))`.
+    fn pawns_by_cell(&self) -> Vec<((usize, usize), (PawnColor, usize))> {
+        let Some(view) = &self.view else {
+            return Vec::new();
+        };
+
+        view.players.iter()
+            .flat_map(|player| player.pawns.iter().enumerate().filter_map(move |(index, spot)| {
+                board::pawn_cell(player.color, *spot, index).map(|cell| (cell, (player.color, index + 1)))
+            }))
+            .collect()
+    }
+
+    fn draw_panel(&self, out: &mut impl Write, controls: &Controls) -> io::Result<()> {
+        let mut row = 1;
+        queue!(out, MoveTo(PANEL_X, row), SetAttribute(Attribute::Bold), Print("PARCHIS"), SetAttribute(Attribute::Reset))?;
+        if let Some(status) = &self.status {
+            queue!(out, MoveTo(PANEL_X, row + 1), SetAttribute(Attribute::Reverse), Print(status), SetAttribute(Attribute::Reset))?;
+        }
+        row += 2;
+
+        let Some(view) = &self.view else {
+            queue!(out, MoveTo(PANEL_X, row), Print("Not at a table yet."))?;
+            return Ok(());
+        };
+
+        let status = match view.status {
+            GameStatus::Waiting => "Waiting for players",
+            GameStatus::Playing => "In progress",
+            GameStatus::Over => "Game over",
+        };
+        queue!(out, MoveTo(PANEL_X, row), Print(status))?;
+        row += 2;
+
+        for player in &view.players {
+            let is_turn = view.turn.as_deref() == Some(player.name.as_str()) && view.status == GameStatus::Playing;
+            let in_house = player.pawns.iter().filter(|spot| **spot == PawnSpot::House).count();
+            let in_goal = player.pawns.iter().filter(|spot| **spot == PawnSpot::Goal).count();
+
+            queue!(out,
+                MoveTo(PANEL_X, row),
+                Print(if is_turn { "> " } else { "  " }),
+                SetBackgroundColor(bright(player.color)), Print("  "), ResetColor,
+                Print(format!(" {:<12} house {} goal {}", player.name, in_house, in_goal)))?;
+            row += 1;
+        }
+        row += 1;
+
+        if let (GameStatus::Playing, Some(turn)) = (view.status, &view.turn) {
+            queue!(out, MoveTo(PANEL_X, row), Print(format!("Turn: {}", turn)))?;
+            row += 1;
+            let dice = view.dice.map_or_else(|| "-".to_string(), |value| value.to_string());
+            queue!(out, MoveTo(PANEL_X, row), Print(format!("Dice: {}", dice)))?;
+            row += 2;
+        }
+
+        if controls.me.as_deref().is_some_and(|me| view.is_turn_of(me)) {
+            let hint = if view.dice.is_none() {
+                "Your turn: Space or R to roll"
+            } else {
+                "1-4 move, O take out, Left/Right + Enter"
+            };
+            queue!(out, MoveTo(PANEL_X, row), SetAttribute(Attribute::Bold), Print(hint), SetAttribute(Attribute::Reset))?;
+        }
+
+        Ok(())
+    }
+
+    fn draw_log(&self, out: &mut impl Write, top: u16, height: u16, width: u16) -> io::Result<()> {
+        let width = width.max(1) as usize;
+        let wrapped: Vec<String> = self.log.iter()
+            .flat_map(|line| wrap(line, width))
+            .collect();
+
+        let end = wrapped.len().saturating_sub(self.scroll.min(wrapped.len()));
+        let start = end.saturating_sub(height as usize);
+        for (offset, line) in wrapped[start..end].iter().enumerate() {
+            queue!(out, MoveTo(0, top + offset as u16), Print(line))?;
+        }
+
+        if self.scroll > 0 {
+            queue!(out, MoveTo(0, top + height), SetAttribute(Attribute::Reverse),
+                Print(format!(" scrolled up {} lines (PageDown to return) ", self.scroll)), SetAttribute(Attribute::Reset))?;
+        }
+        Ok(())
+    }
+}
+
+fn wrap(line: &str, width: usize) -> Vec<String> {
+    if line.is_empty() {
+        return vec![String::new()];
+    }
+    let chars: Vec<char> = line.chars().collect();
+    chars.chunks(width).map(|chunk| chunk.iter().collect()).collect()
+}
+
+fn bright(color: PawnColor) -> Color {
+    match color {
+        PawnColor::Red => Color::Red,
+        PawnColor::Green => Color::Green,
+        PawnColor::Blue => Color::Blue,
+        PawnColor::Yellow => Color::Yellow,
+        PawnColor::Unassigned => Color::Grey,
+    }
+}
+
+fn dark(color: PawnColor) -> Color {
+    match color {
+        PawnColor::Red => Color::DarkRed,
+        PawnColor::Green => Color::DarkGreen,
+        PawnColor::Blue => Color::DarkBlue,
+        PawnColor::Yellow => Color::DarkYellow,
+        PawnColor::Unassigned => Color::DarkGrey,
+    }
+}
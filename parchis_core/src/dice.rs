@@ -0,0 +1,384 @@
+use std::fmt;
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+use sha2::{Digest, Sha256};
+use crate::events::GameEvent;
+
+/// Longest entropy a player may add to fair dice.
+pub const MAX_ENTROPY_LENGTH: usize = 64;
+
+/// Fewest rolls a histogram needs before it is audited, so that every face is
+/// expected at least 5 times.
+pub const MIN_AUDITED_ROLLS: u64 = 30;
+
+/// Chi-squared critical value for 5 degrees of freedom at a 1% significance
+/// level: fair dice go above it once in a hundred audits.
+pub const CHI_SQUARED_CRITICAL: f64 = 15.086;
+
+/// Where a game's randomness comes from: dice rolls and who starts.
+pub trait DiceSource: Send {
+    /// The next roll, from 1 to 6.
+    fn roll(&mut self) -> u8;
+
+    /// A number below `bound`, for drawing the first player.
+    fn pick(&mut self, bound: usize) -> usize;
+
+    /// The seed that reproduces this source, if it has one.
+    fn seed(&self) -> Option<u64> {
+        None
+    }
+
+    /// The hash these dice committed to before the game, if they did.
+    fn commitment(&self) -> Option<String> {
+        None
+    }
+
+    /// The secret behind `commitment`, to be revealed once the game is over.
+    fn secret(&self) -> Option<String> {
+        None
+    }
+
+    /// Takes in a logged event that feeds the dice, such as a player's entropy.
+    fn observe(&mut self, _event: &GameEvent) {}
+}
+
+/// Dice driven by a seeded RNG: the same seed gives the same game.
+pub struct SeededDice {
+    seed: u64,
+    rng: StdRng,
+}
+
+impl SeededDice {
+    pub fn new(seed: u64) -> Self {
+        SeededDice { seed, rng: StdRng::seed_from_u64(seed) }
+    }
+
+    /// Seeded dice with a fresh random seed.
+    pub fn from_entropy() -> Self {
+        SeededDice::new(rand::thread_rng().gen())
+    }
+}
+
+impl DiceSource for SeededDice {
+    fn roll(&mut self) -> u8 {
+        self.rng.gen_range(1..=6)
+    }
+
+    fn pick(&mut self, bound: usize) -> usize {
+        self.rng.gen_range(0..bound.max(1))
+    }
+
+    fn seed(&self) -> Option<u64> {
+        Some(self.seed)
+    }
+}
+
+/// Plays back a fixed list of rolls, starting over when it runs out, and
+/// always lets the first seated player start. For tests.
+pub struct ScriptedDice {
+    rolls: Vec<u8>,
+    next: usize,
+}
+
+impl ScriptedDice {
+    pub fn new(rolls: Vec<u8>) -> Self {
+        ScriptedDice { rolls, next: 0 }
+    }
+
+    /// Reads a script such as `6,6,3,1`.
+    pub fn parse(script: &str) -> Option<Self> {
+        let rolls = script.split(',')
+            .map(|value| value.trim().parse().ok().filter(|value| (1..=6).contains(value)))
+            .collect::<Option<Vec<u8>>>()?;
+        Some(ScriptedDice::new(rolls))
+    }
+}
+
+impl DiceSource for ScriptedDice {
+    fn roll(&mut self) -> u8 {
+        let Some(&value) = self.rolls.get(self.next % self.rolls.len().max(1)) else {
+            return 1;
+        };
+        self.next += 1;
+        value
+    }
+
+    fn pick(&mut self, _bound: usize) -> usize {
+        0
+    }
+}
+
+/// How many times each face came up.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct DiceHistogram {
+    counts: [u64; 6],
+}
+
+impl DiceHistogram {
+    pub fn from_counts(counts: [u64; 6]) -> Self {
+        DiceHistogram { counts }
+    }
+
+    /// Counts a roll; values off the die are ignored.
+    pub fn record(&mut self, value: u8) {
+        if let Some(count) = value.checked_sub(1).and_then(|index| self.counts.get_mut(index as usize)) {
+            *count += 1;
+        }
+    }
+
+    pub fn add(&mut self, other: &DiceHistogram) {
+        for (count, other) in self.counts.iter_mut().zip(other.counts) {
+            *count += other;
+        }
+    }
+
+    /// How many times `face`, from 1 to 6, came up.
+    pub fn count(&self, face: u8) -> u64 {
+        face.checked_sub(1).and_then(|index| self.counts.get(index as usize)).copied().unwrap_or(0)
+    }
+
+    pub fn total(&self) -> u64 {
+        self.counts.iter().sum()
+    }
+
+    /// Pearson's chi-squared statistic against a fair die.
+    pub fn chi_squared(&self) -> f64 {
+        let expected = self.total() as f64 / 6.0;
+        if expected == 0.0 {
+            return 0.0;
+        }
+        self.counts.iter().map(|&count| (count as f64 - expected).powi(2) / expected).sum()
+    }
+
+    /// Whether there are enough rolls to audit and they are further from a
+    /// fair die than `CHI_SQUARED_CRITICAL` allows.
+    pub fn is_anomalous(&self) -> bool {
+        self.total() >= MIN_AUDITED_ROLLS && self.chi_squared() > CHI_SQUARED_CRITICAL
+    }
+}
+
+/// `1:5 2:7 3:4 4:6 5:3 6:4`
+impl fmt::Display for DiceHistogram {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let faces: Vec<String> = (1..=6).map(|face| format!("{}:{}", face, self.count(face))).collect();
+        write!(f, "{}", faces.join(" "))
+    }
+}
+
+/// Commit–reveal dice the players can check.
+///
+/// The server draws a secret and publishes its SHA-256 before the game; the
+/// players then add their own entropy. Roll `n` (counting from 1) is
+/// `draw(n) % 6 + 1`, where `draw(n)` is the first 8 bytes, big-endian, of
+/// SHA-256 over the secret, each contribution as `player:entropy\n` in the
+/// order they came in, and `n` as 8 big-endian bytes. The first player is
+/// `draw(0)`, with no entropy yet, modulo the number of seats. Once the secret
+/// is revealed anyone can redo every roll with `FairDiceCheck`. Players who have
+/// not added entropy by the time the server stops waiting are left out.
+pub struct FairDice {
+    secret: [u8; 32],
+    entropy: Vec<(String, String)>,
+    rolls: u64,
+}
+
+impl FairDice {
+    pub fn new() -> Self {
+        FairDice { secret: rand::thread_rng().gen(), entropy: Vec::new(), rolls: 0 }
+    }
+
+    /// Fair dice with a known secret, given in hex, such as those of a saved game.
+    pub fn from_secret(secret: &str) -> Option<Self> {
+        let secret = from_hex(secret)?.try_into().ok()?;
+        Some(FairDice { secret, entropy: Vec::new(), rolls: 0 })
+    }
+}
+
+impl Default for FairDice {
+    fn default() -> Self {
+        FairDice::new()
+    }
+}
+
+impl DiceSource for FairDice {
+    fn roll(&mut self) -> u8 {
+        self.rolls += 1;
+        face(draw(&self.secret, &self.entropy, self.rolls))
+    }
+
+    /// The first player is drawn when the game starts, before anyone can add
+    /// entropy, so this draw comes from the secret alone and players' entropy
+    /// only goes into the rolls.
+    fn pick(&mut self, bound: usize) -> usize {
+        (draw(&self.secret, &[], 0) % bound.max(1) as u64) as usize
+    }
+
+    fn commitment(&self) -> Option<String> {
+        Some(to_hex(&Sha256::digest(self.secret)))
+    }
+
+    fn secret(&self) -> Option<String> {
+        Some(to_hex(&self.secret))
+    }
+
+    fn observe(&mut self, event: &GameEvent) {
+        if let GameEvent::Entropy { player, value } = event {
+            self.entropy.push((player.clone(), value.clone()));
+        }
+    }
+}
+
+/// Checks a game's fair dice from its event log, fed one event at a time.
+#[derive(Default)]
+pub struct FairDiceCheck {
+    commitment: Option<String>,
+    entropy: Vec<(String, String)>,
+    /// Every roll, with how many contributions had come in by then.
+    rolls: Vec<(String, u8, usize)>,
+    secret: Option<String>,
+}
+
+impl FairDiceCheck {
+    pub fn new() -> Self {
+        FairDiceCheck::default()
+    }
+
+    pub fn observe(&mut self, event: &GameEvent) {
+        match event {
+            GameEvent::Committed { commitment } => self.commitment = Some(commitment.clone()),
+            GameEvent::Entropy { player, value } => self.entropy.push((player.clone(), value.clone())),
+            GameEvent::Rolled { player, value } => self.rolls.push((player.clone(), *value, self.entropy.len())),
+            GameEvent::Revealed { secret } => self.secret = Some(secret.clone()),
+            _ => {}
+        }
+    }
+
+    /// Checks the revealed secret against the commitment and works out every
+    /// roll again, returning how many rolls were checked.
+    pub fn verify(&self) -> Result<usize, String> {
+        let commitment = self.commitment.as_ref().ok_or("the dice were not committed to")?;
+        let secret = self.secret.as_ref().ok_or("the dice secret has not been revealed")?;
+        let secret = from_hex(secret).ok_or("the revealed secret is not valid hex")?;
+        if !to_hex(&Sha256::digest(&secret)).eq_ignore_ascii_case(commitment) {
+            return Err("the revealed secret does not match the commitment".to_string());
+        }
+
+        for (index, (player, value, contributions)) in self.rolls.iter().enumerate() {
+            let expected = face(draw(&secret, &self.entropy[..*contributions], index as u64 + 1));
+            if expected != *value {
+                return Err(format!("roll {} ({}) was a {} but the dice give a {}", index + 1, player, value, expected));
+            }
+        }
+        Ok(self.rolls.len())
+    }
+}
+
+/// Checks entropy offered by a player: 1 to `MAX_ENTROPY_LENGTH` visible ASCII characters.
+pub fn is_valid_entropy(value: &str) -> bool {
+    (1..=MAX_ENTROPY_LENGTH).contains(&value.len()) && value.bytes().all(|byte| byte.is_ascii_graphic())
+}
+
+/// Fresh entropy for a player to add to fair dice.
+pub fn random_entropy() -> String {
+    to_hex(&rand::thread_rng().gen::<[u8; 16]>())
+}
+
+fn draw(secret: &[u8], entropy: &[(String, String)], counter: u64) -> u64 {
+    let mut hasher = Sha256::new();
+    hasher.update(secret);
+    for (player, value) in entropy {
+        hasher.update(format!("{}:{}\n", player, value));
+    }
+    hasher.update(counter.to_be_bytes());
+    let digest = hasher.finalize();
+    let mut first = [0u8; 8];
+    first.copy_from_slice(&digest[..8]);
+    u64::from_be_bytes(first)
+}
+
+fn face(draw: u64) -> u8 {
+    (draw % 6) as u8 + 1
+}
+
+fn to_hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|byte| format!("{:02x}", byte)).collect()
+}
+
+fn from_hex(hex: &str) -> Option<Vec<u8>> {
+    if !hex.len().is_multiple_of(2) || !hex.is_ascii() {
+        return None;
+    }
+    (0..hex.len()).step_by(2).map(|index| u8::from_str_radix(&hex[index..index + 2], 16).ok()).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// The events of a fair game in which ana added entropy and rolled `rolls` times.
+    fn fair_game(rolls: usize) -> Vec<GameEvent> {
+        let mut dice = FairDice::new();
+        let entropy = GameEvent::Entropy { player: "ana".to_string(), value: "abc".to_string() };
+        dice.observe(&entropy);
+        let mut events = vec![GameEvent::Committed { commitment: dice.commitment().unwrap() }, entropy];
+        for _ in 0..rolls {
+            events.push(GameEvent::Rolled { player: "ana".to_string(), value: dice.roll() });
+        }
+        events.push(GameEvent::Revealed { secret: dice.secret().unwrap() });
+        events
+    }
+
+    fn check(events: &[GameEvent]) -> Result<usize, String> {
+        let mut check = FairDiceCheck::new();
+        for event in events {
+            check.observe(event);
+        }
+        check.verify()
+    }
+
+    #[test]
+    fn fair_dice_check_accepts_honest_rolls() {
+        assert_eq!(check(&fair_game(10)), Ok(10));
+    }
+
+    #[test]
+    fn fair_dice_check_catches_a_changed_roll() {
+        let mut events = fair_game(10);
+        if let GameEvent::Rolled { value, .. } = &mut events[5] {
+            *value = *value % 6 + 1;
+        }
+        assert!(check(&events).unwrap_err().starts_with("roll 4 "));
+    }
+
+    #[test]
+    fn fair_dice_check_catches_a_swapped_secret() {
+        let mut events = fair_game(3);
+        *events.last_mut().unwrap() = GameEvent::Revealed { secret: FairDice::new().secret().unwrap() };
+        assert_eq!(check(&events), Err("the revealed secret does not match the commitment".to_string()));
+    }
+
+    #[test]
+    fn fair_dice_check_needs_the_secret() {
+        let mut events = fair_game(3);
+        events.pop();
+        assert_eq!(check(&events), Err("the dice secret has not been revealed".to_string()));
+    }
+
+    #[test]
+    fn chi_squared_of_even_rolls_is_zero() {
+        assert_eq!(DiceHistogram::from_counts([10; 6]).chi_squared(), 0.0);
+        assert_eq!(DiceHistogram::default().chi_squared(), 0.0);
+    }
+
+    #[test]
+    fn chi_squared_of_loaded_dice() {
+        let loaded = DiceHistogram::from_counts([60, 0, 0, 0, 0, 0]);
+        // Expected 10 of each face: 50²/10 for the ones and 10²/10 for each other face.
+        assert!((loaded.chi_squared() - 300.0).abs() < 1e-9);
+        assert!(loaded.is_anomalous());
+    }
+
+    #[test]
+    fn too_few_rolls_are_not_audited() {
+        assert!(!DiceHistogram::from_counts([29, 0, 0, 0, 0, 0]).is_anomalous());
+    }
+}
@@ -0,0 +1,138 @@
+use std::fmt;
+use std::fs;
+use std::io;
+use std::path::Path;
+use crate::rules::Rules;
+
+const REPLAY_HEADER: &str = "PARCHIS_REPLAY";
+
+/// Something that happened in a game, in the order it happened.
+///
+/// Players are named by their lowercased key, as in the `STATE` line, and
+/// pawn spots use the same codes: `H`, `G` or a board position.
+#[derive(Clone, Debug, PartialEq)]
+pub enum GameEvent {
+    Joined { player: String },
+    Left { player: String },
+    ColorChosen { player: String, color: String },
+    /// Fair dice: the hash of the secret the rolls will come from.
+    Committed { commitment: String },
+    Started { first: String },
+    /// Fair dice: randomness a player added to the rolls.
+    Entropy { player: String, value: String },
+    Turn { player: String },
+    Rolled { player: String, value: u8 },
+    MovedOut { player: String, pawn: usize },
+    Moved { player: String, pawn: usize, to: String },
+    /// A player got every pawn home and took `place`, with the game still going on.
+    Finished { player: String, place: usize },
+    Won { player: String },
+    /// Fair dice: the secret behind the commitment, once the game is over.
+    Revealed { secret: String },
+}
+
+/// One event per line: `JOIN ana`, `LEAVE ana`, `COLOR ana Red`, `COMMIT <hash>`, `START ana`,
+/// `ENTROPY ana <value>`, `TURN bob`, `ROLL ana 6`, `MOVE_OUT ana 1`, `MOVE ana 1 12`,
+/// `FINISH ana 2`, `WIN ana` and `REVEAL <secret>`.
+impl fmt::Display for GameEvent {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            GameEvent::Joined { player } => write!(f, "JOIN {}", player),
+            GameEvent::Left { player } => write!(f, "LEAVE {}", player),
+            GameEvent::ColorChosen { player, color } => write!(f, "COLOR {} {}", player, color),
+            GameEvent::Committed { commitment } => write!(f, "COMMIT {}", commitment),
+            GameEvent::Started { first } => write!(f, "START {}", first),
+            GameEvent::Entropy { player, value } => write!(f, "ENTROPY {} {}", player, value),
+            GameEvent::Turn { player } => write!(f, "TURN {}", player),
+            GameEvent::Rolled { player, value } => write!(f, "ROLL {} {}", player, value),
+            GameEvent::MovedOut { player, pawn } => write!(f, "MOVE_OUT {} {}", player, pawn),
+            GameEvent::Moved { player, pawn, to } => write!(f, "MOVE {} {} {}", player, pawn, to),
+            GameEvent::Finished { player, place } => write!(f, "FINISH {} {}", player, place),
+            GameEvent::Won { player } => write!(f, "WIN {}", player),
+            GameEvent::Revealed { secret } => write!(f, "REVEAL {}", secret),
+        }
+    }
+}
+
+impl GameEvent {
+    pub fn parse(line: &str) -> Option<GameEvent> {
+        let parts: Vec<&str> = line.split_whitespace().collect();
+        let event = match parts.as_slice() {
+            ["JOIN", player] => GameEvent::Joined { player: player.to_string() },
+            ["LEAVE", player] => GameEvent::Left { player: player.to_string() },
+            ["COLOR", player, color] => GameEvent::ColorChosen { player: player.to_string(), color: color.to_string() },
+            ["COMMIT", commitment] => GameEvent::Committed { commitment: commitment.to_string() },
+            ["START", first] => GameEvent::Started { first: first.to_string() },
+            ["ENTROPY", player, value] => GameEvent::Entropy { player: player.to_string(), value: value.to_string() },
+            ["TURN", player] => GameEvent::Turn { player: player.to_string() },
+            ["ROLL", player, value] => GameEvent::Rolled { player: player.to_string(), value: value.parse().ok()? },
+            ["MOVE_OUT", player, pawn] => GameEvent::MovedOut { player: player.to_string(), pawn: pawn.parse().ok()? },
+            ["MOVE", player, pawn, to] => GameEvent::Moved { player: player.to_string(), pawn: pawn.parse().ok()?, to: to.to_string() },
+            ["FINISH", player, place] => GameEvent::Finished { player: player.to_string(), place: place.parse().ok()? },
+            ["WIN", player] => GameEvent::Won { player: player.to_string() },
+            ["REVEAL", secret] => GameEvent::Revealed { secret: secret.to_string() },
+            _ => return None,
+        };
+        Some(event)
+    }
+}
+
+/// A finished game as stored on disk: a header line with the rules needed to
+/// draw the board and the dice seed, if any, followed by the event log.
+pub struct Replay {
+    pub pawns_per_player: usize,
+    pub exit_roll: u8,
+    pub seed: Option<u64>,
+    pub events: Vec<GameEvent>,
+}
+
+impl Replay {
+    pub fn new(rules: &Rules, events: &[GameEvent], seed: Option<u64>) -> Self {
+        Replay {
+            pawns_per_player: rules.pawns_per_player,
+            exit_roll: rules.exit_roll,
+            seed,
+            events: events.to_vec(),
+        }
+    }
+
+    pub fn save(&self, path: &Path) -> io::Result<()> {
+        let mut contents = format!("{} pawns={} exit={}", REPLAY_HEADER, self.pawns_per_player, self.exit_roll);
+        if let Some(seed) = self.seed {
+            contents.push_str(&format!(" seed={}", seed));
+        }
+        contents.push('\n');
+        for event in &self.events {
+            contents.push_str(&format!("{}\n", event));
+        }
+        fs::write(path, contents)
+    }
+
+    pub fn load(path: &Path) -> io::Result<Replay> {
+        let contents = fs::read_to_string(path)?;
+        let invalid = |reason: String| io::Error::new(io::ErrorKind::InvalidData, reason);
+
+        let mut lines = contents.lines();
+        let header = lines.next().unwrap_or_default();
+        let mut fields = header.split_whitespace();
+        if fields.next() != Some(REPLAY_HEADER) {
+            return Err(invalid("not a parchis replay file".to_string()));
+        }
+
+        let mut replay = Replay { pawns_per_player: 4, exit_roll: 6, seed: None, events: Vec::new() };
+        for field in fields {
+            match field.split_once('=') {
+                Some(("pawns", value)) => replay.pawns_per_player = value.parse().map_err(|_| invalid(format!("bad header field '{}'", field)))?,
+                Some(("exit", value)) => replay.exit_roll = value.parse().map_err(|_| invalid(format!("bad header field '{}'", field)))?,
+                Some(("seed", value)) => replay.seed = Some(value.parse().map_err(|_| invalid(format!("bad header field '{}'", field)))?),
+                _ => {}
+            }
+        }
+
+        for (index, line) in lines.enumerate().filter(|(_, line)| !line.trim().is_empty()) {
+            let event = GameEvent::parse(line).ok_or_else(|| invalid(format!("line {}: unknown event '{}'", index + 2, line)))?;
+            replay.events.push(event);
+        }
+        Ok(replay)
+    }
+}
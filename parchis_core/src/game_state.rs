@@ -0,0 +1,1487 @@
+use std::collections::HashMap;
+use std::fmt;
+use std::fs;
+use std::io;
+use std::path::Path;
+use rand::Rng;
+use crate::dice::{is_valid_entropy, DiceHistogram, DiceSource, FairDice, SeededDice, MAX_ENTROPY_LENGTH};
+use crate::events::GameEvent;
+use crate::rules::Rules;
+
+/// Identifies whoever occupies a seat: a network connection on the server,
+/// or a seat at the keyboard in the client's local mode.
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+pub struct ClientId(pub u64);
+
+impl ClientId {
+    /// Held by the seats of a game loaded from disk until their players take them back.
+    /// Real ids count from 1, so no connection or local seat ever holds it.
+    pub const UNCLAIMED: ClientId = ClientId(0);
+}
+
+impl fmt::Display for ClientId {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "#{}", self.0)
+    }
+}
+
+/// Longest name a player can sit down with.
+pub const MAX_NAME_LENGTH: usize = 16;
+
+/// The one way a player is identified inside a game: their name, lowercased.
+/// Every `Game` method that takes a player name accepts it in any case, and
+/// events, the `STATE` line and turn names all use this form.
+pub fn player_id(name: &str) -> String {
+    name.to_lowercase()
+}
+
+/// Why a name cannot be used at a table.
+#[derive(Debug, PartialEq)]
+pub enum NameError {
+    Empty,
+    TooLong,
+    InvalidCharacters,
+    Taken(String),
+}
+
+impl fmt::Display for NameError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            NameError::Empty => write!(f, "Please provide a name."),
+            NameError::TooLong => write!(f, "Names can be at most {} characters long.", MAX_NAME_LENGTH),
+            NameError::InvalidCharacters => write!(f, "Names can only use letters, digits, '_' and '-'."),
+            NameError::Taken(name) => write!(f, "The name {} is already taken at this table.", name),
+        }
+    }
+}
+
+impl std::error::Error for NameError {}
+
+/// Checks that `name` can be a single word in commands and in the `STATE` line.
+pub fn validate_name(name: &str) -> Result<(), NameError> {
+    if name.is_empty() {
+        return Err(NameError::Empty);
+    }
+    if name.chars().count() > MAX_NAME_LENGTH {
+        return Err(NameError::TooLong);
+    }
+    if !name.chars().all(|c| c.is_alphanumeric() || c == '_' || c == '-') {
+        return Err(NameError::InvalidCharacters);
+    }
+    Ok(())
+}
+
+/// What a roll lets the current player do next.
+pub enum RollOutcome {
+    /// The only possible move is taking a pawn out of the house.
+    MustMoveOut,
+    /// The player may take a pawn out or move one on the board.
+    MoveOrMoveOut,
+    /// The player has to pick one of several pawns on the board.
+    ChoosePawn,
+    /// The only pawn on the board was moved for the player.
+    AutoMoved(MoveOutcome),
+    /// Nothing can be moved with this roll and the turn has passed.
+    NoMove,
+}
+
+pub struct MoveOutcome {
+    pub pawn: usize,
+    pub reached_goal: bool,
+    /// The place the player took if this move got their last pawn home.
+    pub place: Option<usize>,
+    pub game_over: bool,
+}
+
+#[derive(Debug, PartialEq)]
+pub enum TurnError {
+    NotStarted,
+    GameOver,
+    NotYourTurn(String),
+    AlreadyRolled(u8),
+    NoRoll,
+    InvalidPawn,
+    CannotMoveOut,
+    SeatsUnclaimed(Vec<String>),
+    /// Fair dice cannot roll until every player has added entropy.
+    AwaitingEntropy(Vec<String>),
+}
+
+/// Why the host cannot start the game yet.
+#[derive(Debug, PartialEq)]
+pub enum StartError {
+    AlreadyStarted,
+    NotEnoughPlayers,
+    NoColor(Vec<String>),
+    NotReady(Vec<String>),
+}
+
+impl fmt::Display for StartError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            StartError::AlreadyStarted => write!(f, "Game has already started."),
+            StartError::NotEnoughPlayers => write!(f, "Not enough players to start the game."),
+            StartError::NoColor(names) => write!(f, "Waiting for {} to choose a color.", names.join(", ")),
+            StartError::NotReady(names) => write!(f, "Waiting for {} to type 'READY'.", names.join(", ")),
+        }
+    }
+}
+
+impl std::error::Error for StartError {}
+
+impl fmt::Display for TurnError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            TurnError::NotStarted => write!(f, "The game hasn't started yet."),
+            TurnError::GameOver => write!(f, "The game is over."),
+            TurnError::NotYourTurn(current) => write!(f, "It's not your turn, it's {}'s turn.", current),
+            TurnError::AlreadyRolled(value) => write!(f, "You already rolled a {}; move a pawn first.", value),
+            TurnError::NoRoll => write!(f, "Roll the dice first."),
+            TurnError::InvalidPawn => write!(f, "Invalid pawn number, pawn not on board, or pawn already in goal."),
+            TurnError::CannotMoveOut => write!(f, "You cannot move a pawn out right now."),
+            TurnError::SeatsUnclaimed(names) => write!(f, "Waiting for {} to take their seats again.", names.join(", ")),
+            TurnError::AwaitingEntropy(names) => write!(f, "Waiting for {} to add entropy to the dice with 'ENTROPY <text>'.", names.join(", ")),
+        }
+    }
+}
+
+impl std::error::Error for TurnError {}
+
+/// Why a player's entropy was not added to the dice.
+#[derive(Debug, PartialEq)]
+pub enum EntropyError {
+    NotFairDice,
+    NotStarted,
+    AlreadyAdded,
+    TooLate,
+    Invalid,
+}
+
+impl fmt::Display for EntropyError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            EntropyError::NotFairDice => write!(f, "This game's dice do not take entropy."),
+            EntropyError::NotStarted => write!(f, "The game hasn't started yet."),
+            EntropyError::AlreadyAdded => write!(f, "You have already added entropy to the dice."),
+            EntropyError::TooLate => write!(f, "The dice no longer take entropy; it can only be added before the first roll."),
+            EntropyError::Invalid => write!(f, "Entropy must be 1 to {} visible characters without spaces.", MAX_ENTROPY_LENGTH),
+        }
+    }
+}
+
+impl std::error::Error for EntropyError {}
+
+#[derive(PartialEq)]
+pub enum GameStatus {
+    WaitingForPlayers,
+    InProgress,
+    GameOver,
+}
+
+#[derive(PartialEq, Debug, Clone)]
+pub enum Color {
+    Red,
+    Green,
+    Blue,
+    Yellow,
+    Unassigned,
+}
+
+impl Color {
+    /// Reads back a color written with `{:?}`, as in saves and event logs.
+    pub fn from_name(name: &str) -> Option<Color> {
+        match name {
+            "Red" => Some(Color::Red),
+            "Green" => Some(Color::Green),
+            "Blue" => Some(Color::Blue),
+            "Yellow" => Some(Color::Yellow),
+            "Unassigned" => Some(Color::Unassigned),
+            _ => None,
+        }
+    }
+}
+
+/// Board positions from this value on are the goal.
+const GOAL_POSITION: u8 = 58;
+
+enum PawnPosition {
+    House,
+    Board(u8),
+    Goal,
+}
+
+impl PawnPosition {
+    fn parse(code: &str) -> Option<PawnPosition> {
+        match code {
+            "H" => Some(PawnPosition::House),
+            "G" => Some(PawnPosition::Goal),
+            position => position.parse().ok().map(PawnPosition::Board),
+        }
+    }
+
+    fn code(&self) -> String {
+        match self {
+            PawnPosition::House => "H".to_string(),
+            PawnPosition::Board(pos) => pos.to_string(),
+            PawnPosition::Goal => "G".to_string(),
+        }
+    }
+}
+
+struct Pawn {
+    position: PawnPosition,
+}
+
+impl Pawn {
+    pub fn new() -> Self {
+        Pawn { position: PawnPosition::House }
+    }
+}
+
+pub struct Player {
+    name: String,
+    pawns: Vec<Pawn>,
+    color: Color,
+    pub client_id: ClientId,
+    /// Registered account playing this seat; `None` for guests.
+    pub account_id: Option<i64>,
+    /// Secret handed to the client on `JOIN` so it can take the seat back after a disconnect.
+    session_token: String,
+    is_fully_registered: bool,
+    /// Ready for the host to start; cleared whenever the color changes.
+    ready: bool,
+}
+
+impl Player {
+
+    pub fn is_ready(&self) -> bool {
+        self.ready
+    }
+
+    pub fn mark_as_fully_registered(&mut self) {
+        self.is_fully_registered = true;
+    }
+
+    /// The name as the player typed it, for display.
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    pub fn session_token(&self) -> &str {
+        &self.session_token
+    }
+
+    pub fn color(&self) -> &Color {
+        &self.color
+    }
+
+    pub fn set_color(&mut self, new_color: Color) {
+        self.color = new_color;
+    }
+
+    pub fn initialize_pawns(&mut self) {
+        for pawn in &mut self.pawns {
+            pawn.position = PawnPosition::House;
+        }
+    }
+
+    pub fn get_pawn_positions(&self) -> String {
+        self.pawns.iter().enumerate().map(|(index, pawn)| {
+            let position = match &pawn.position {
+                PawnPosition::House => "House".to_string(),
+                PawnPosition::Board(pos) => pos.to_string(),
+                PawnPosition::Goal => "Goal".to_string(),
+            };
+            format!("Pawn {}: {}", index + 1, position)
+        }).collect::<Vec<_>>().join(", ")
+    }
+
+    pub fn get_pawn_codes(&self) -> String {
+        self.pawns.iter().map(|pawn| pawn.position.code()).collect::<Vec<_>>().join(",")
+    }
+
+    pub fn get_pawn_counts(&self) -> (usize, usize) {
+        let pawns_in_house = self.pawns.iter().filter(|p| matches!(p.position, PawnPosition::House)).count();
+        let pawns_on_board = self.pawns.iter().filter(|p| matches!(p.position, PawnPosition::Board(_))).count();
+        (pawns_in_house, pawns_on_board)
+    }
+
+    /// Takes the first pawn in the house out to the start square and returns its number.
+    pub fn move_pawn_out(&mut self) -> Option<usize> {
+        let index = self.pawns.iter().position(|p| matches!(p.position, PawnPosition::House))?;
+        self.pawns[index].position = PawnPosition::Board(1);
+        Some(index + 1)
+    }
+
+    pub fn is_pawn_in_goal(&self, pawn_number: usize) -> bool {
+        self.pawns.get(pawn_number - 1)
+            .is_some_and(|pawn| matches!(pawn.position, PawnPosition::Goal))
+    }
+
+    pub fn all_pawns_in_goal(&self) -> bool {
+        self.pawns.iter().all(|pawn| matches!(pawn.position, PawnPosition::Goal))
+    }
+
+
+    pub fn first_pawn_on_board_number(&self) -> Option<usize> {
+        self.pawns.iter().enumerate()
+            .find_map(|(index, pawn)| match pawn.position {
+                PawnPosition::Board(pos) if pos < GOAL_POSITION => Some(index + 1),
+                _ => None,
+            })
+    }
+
+    pub fn move_pawn(&mut self, pawn_number: usize, dice_value: u8) {
+        if pawn_number == 0 || pawn_number > self.pawns.len() {
+            return; 
+        }
+        let pawn_index = pawn_number - 1;
+
+        if let Some(pawn) = self.pawns.get_mut(pawn_index) {
+            if let PawnPosition::Board(pos) = pawn.position {
+                let new_position = (pos as u32 + dice_value as u32) as u8;
+                if new_position >= GOAL_POSITION {
+                    pawn.position = PawnPosition::Goal;
+                } else {
+                    pawn.position = PawnPosition::Board(new_position);
+                }
+            }
+        }
+    }
+
+    /// How far the player's pawns have come in total, for ranking players who did not win.
+    pub fn progress(&self) -> u32 {
+        self.pawns.iter().map(|pawn| match pawn.position {
+            PawnPosition::House => 0,
+            PawnPosition::Board(pos) => pos as u32,
+            PawnPosition::Goal => GOAL_POSITION as u32,
+        }).sum()
+    }
+
+    /// Numbers and positions of the pawns currently on the board.
+    pub fn pawns_on_board(&self) -> Vec<(usize, u8)> {
+        self.pawns.iter().enumerate()
+            .filter_map(|(index, pawn)| match pawn.position {
+                PawnPosition::Board(pos) => Some((index + 1, pos)),
+                _ => None,
+            })
+            .collect()
+    }
+
+    pub fn is_valid_pawn_number(&self, pawn_number: usize) -> bool {
+        if pawn_number == 0 || pawn_number > self.pawns.len() {
+            return false;
+        }
+        matches!(self.pawns[pawn_number - 1].position, PawnPosition::Board(_))
+    }
+
+}
+
+pub struct Game {
+    players: HashMap<String, Player>,
+    current_turn: Option<String>,
+    last_dice_roll: HashMap<String, u8>,
+    /// The current player's roll while it still has to be played.
+    pending_roll: Option<u8>,
+    status: GameStatus,
+    rules: Rules,
+    /// Append-only log of everything that happened, written out as the replay.
+    events: Vec<GameEvent>,
+    /// Players who got every pawn home, in the order they did.
+    placements: Vec<String>,
+    /// The player who starts the game and may kick others; the first to join.
+    host: Option<String>,
+    dice: Box<dyn DiceSource>,
+    /// Every player's rolls in this game, face by face.
+    dice_histograms: HashMap<String, DiceHistogram>,
+    /// Fair dice stopped waiting for entropy before everyone added theirs.
+    entropy_closed: bool,
+}
+
+impl Game {
+    pub fn new(rules: Rules) -> Self {
+        Game {
+            players: HashMap::new(),
+            current_turn: None,
+            last_dice_roll: HashMap::new(),
+            pending_roll: None,
+            status: GameStatus::WaitingForPlayers,
+            rules,
+            events: Vec::new(),
+            placements: Vec::new(),
+            host: None,
+            dice: Box::new(SeededDice::from_entropy()),
+            dice_histograms: HashMap::new(),
+            entropy_closed: false,
+        }
+    }
+
+    pub fn rules(&self) -> &Rules {
+        &self.rules
+    }
+
+    pub fn events(&self) -> &[GameEvent] {
+        &self.events
+    }
+
+    /// Replaces where rolls and the draw for the first player come from.
+    pub fn set_dice(&mut self, dice: Box<dyn DiceSource>) {
+        self.dice = dice;
+    }
+
+    /// The seed that reproduces this game's dice, if they are seeded.
+    pub fn dice_seed(&self) -> Option<u64> {
+        self.dice.seed()
+    }
+
+    /// The hash fair dice committed to, if the game has fair dice.
+    pub fn dice_commitment(&self) -> Option<String> {
+        self.dice.commitment()
+    }
+
+    /// The fair dice secret, once the game is over and it has been revealed.
+    pub fn revealed_secret(&self) -> Option<&str> {
+        self.events.iter().find_map(|event| match event {
+            GameEvent::Revealed { secret } => Some(secret.as_str()),
+            _ => None,
+        })
+    }
+
+    /// How `player`'s rolls in this game came out.
+    pub fn dice_histogram(&self, player: &str) -> DiceHistogram {
+        self.dice_histograms.get(&player_id(player)).copied().unwrap_or_default()
+    }
+
+    /// Every roll in this game, whoever rolled it.
+    pub fn total_dice_histogram(&self) -> DiceHistogram {
+        let mut total = DiceHistogram::default();
+        for histogram in self.dice_histograms.values() {
+            total.add(histogram);
+        }
+        total
+    }
+
+    /// Players who still have to add entropy before fair dice can roll, in seat order.
+    /// Once the dice have rolled nobody is waited for, even after a restart.
+    pub fn awaiting_entropy(&self) -> Vec<String> {
+        if self.entropy_closed
+            || !self.events.iter().any(|event| matches!(event, GameEvent::Committed { .. }))
+            || self.events.iter().any(|event| matches!(event, GameEvent::Rolled { .. }))
+        {
+            return Vec::new();
+        }
+        self.seat_order().into_iter()
+            .filter(|name| !self.events.iter().any(|event| matches!(event, GameEvent::Entropy { player, .. } if player == name)))
+            .collect()
+    }
+
+    /// Adds `player`'s entropy to fair dice. Every player adds theirs once,
+    /// after the dice are committed and before the first roll.
+    pub fn add_entropy(&mut self, player: &str, value: &str) -> Result<(), EntropyError> {
+        let player = player_id(player);
+        if self.dice.commitment().is_none() {
+            return Err(EntropyError::NotFairDice);
+        }
+        if self.status != GameStatus::InProgress {
+            return Err(EntropyError::NotStarted);
+        }
+        if self.entropy_closed || self.events.iter().any(|event| matches!(event, GameEvent::Rolled { .. })) {
+            return Err(EntropyError::TooLate);
+        }
+        if !self.awaiting_entropy().contains(&player) {
+            return Err(EntropyError::AlreadyAdded);
+        }
+        if !is_valid_entropy(value) {
+            return Err(EntropyError::Invalid);
+        }
+
+        let event = GameEvent::Entropy { player, value: value.to_string() };
+        self.dice.observe(&event);
+        self.events.push(event);
+        Ok(())
+    }
+
+    /// Lets fair dice roll with the entropy added so far, for when some players
+    /// never add theirs. Returns who was still missing.
+    pub fn close_entropy(&mut self) -> Vec<String> {
+        let missing = self.awaiting_entropy();
+        self.entropy_closed = true;
+        missing
+    }
+
+    /// Players in the order they sat down, which is also the turn order.
+    fn seat_order(&self) -> Vec<String> {
+        let mut seats: Vec<String> = Vec::new();
+        for event in &self.events {
+            if let GameEvent::Joined { player } = event {
+                if self.players.contains_key(player) && !seats.contains(player) {
+                    seats.push(player.clone());
+                }
+            }
+        }
+        // Seats of a game saved without its log.
+        let mut unlogged: Vec<String> = self.players.keys().filter(|name| !seats.contains(name)).cloned().collect();
+        unlogged.sort();
+        seats.extend(unlogged);
+        seats
+    }
+
+    pub fn get_board_state(&self) -> String {
+        self.players.values().map(|player| {
+            format!("{}: {}", player.name, player.get_pawn_positions())
+        }).collect::<Vec<_>>().join("\n")
+    }
+
+
+
+    /// Seats a new player and returns its session token. Names must be valid
+    /// and not yet taken at this table, whatever their case.
+    pub fn add_player(&mut self, name: String, client_id: ClientId) -> Result<String, NameError> {
+        validate_name(&name)?;
+        let id = player_id(&name);
+        if let Some(existing) = self.players.get(&id) {
+            return Err(NameError::Taken(existing.name.clone()));
+        }
+
+        let session_token = format!("{:032x}", rand::thread_rng().gen::<u128>());
+        let player = Player {
+            name: name.clone(),
+            pawns: (0..self.rules.pawns_per_player).map(|_| Pawn::new()).collect(),
+            color: Color::Unassigned,
+            client_id,
+            account_id: None,
+            session_token: session_token.clone(),
+            is_fully_registered: false,
+            ready: false,
+        };
+        if self.host.is_none() {
+            self.host = Some(id.clone());
+        }
+        self.events.push(GameEvent::Joined { player: id.clone() });
+        self.players.insert(id, player);
+        Ok(session_token)
+    }
+
+    /// Frees `name`'s seat before the game starts. Returns whether there was one to free.
+    pub fn remove_player(&mut self, name: &str) -> bool {
+        let name = &player_id(name);
+        if self.status != GameStatus::WaitingForPlayers || self.players.remove(name).is_none() {
+            return false;
+        }
+        self.last_dice_roll.remove(name);
+        self.events.push(GameEvent::Left { player: name.clone() });
+        if self.host.as_ref() == Some(name) {
+            // The longest seated player takes over.
+            self.host = self.events.iter()
+                .filter_map(|event| match event {
+                    GameEvent::Joined { player } if self.players.contains_key(player) => Some(player.clone()),
+                    _ => None,
+                })
+                .next();
+        }
+        true
+    }
+
+    pub fn host(&self) -> Option<&String> {
+        self.host.as_ref()
+    }
+
+    /// Hands the host role to another seated player.
+    pub fn set_host(&mut self, name: &str) -> bool {
+        let name = player_id(name);
+        if !self.players.contains_key(&name) {
+            return false;
+        }
+        self.host = Some(name);
+        true
+    }
+
+    /// Hands the host role on when nobody sits in the host's seat any more: to
+    /// the next player in seat order who is still there. Returns the new host.
+    pub fn replace_absent_host(&mut self) -> Option<String> {
+        let host = self.host.clone()?;
+        if self.players.get(&host).is_some_and(|player| player.client_id != ClientId::UNCLAIMED) {
+            return None;
+        }
+        let seats = self.seat_order();
+        let start = seats.iter().position(|name| *name == host).unwrap_or(0);
+        let next = (1..seats.len())
+            .map(|offset| &seats[(start + offset) % seats.len()])
+            .find(|name| self.players[*name].client_id != ClientId::UNCLAIMED)?
+            .clone();
+        self.host = Some(next.clone());
+        Some(next)
+    }
+
+    /// Marks a player ready or not. Only players with a color can be ready.
+    pub fn set_ready(&mut self, name: &str, ready: bool) -> bool {
+        match self.players.get_mut(&player_id(name)) {
+            Some(player) if player.is_fully_registered => {
+                player.ready = ready;
+                true
+            }
+            _ => false,
+        }
+    }
+
+    /// Whether the game can start: enough players, all with a color and ready.
+    pub fn check_start(&self) -> Result<(), StartError> {
+        if self.status != GameStatus::WaitingForPlayers {
+            return Err(StartError::AlreadyStarted);
+        }
+        if !self.has_enough_players() {
+            return Err(StartError::NotEnoughPlayers);
+        }
+        let mut without_color: Vec<String> = self.players.iter()
+            .filter(|(_, player)| !player.is_fully_registered)
+            .map(|(name, _)| name.clone())
+            .collect();
+        if !without_color.is_empty() {
+            without_color.sort();
+            return Err(StartError::NoColor(without_color));
+        }
+        let mut not_ready: Vec<String> = self.players.iter()
+            .filter(|(_, player)| !player.ready)
+            .map(|(name, _)| name.clone())
+            .collect();
+        if !not_ready.is_empty() {
+            not_ready.sort();
+            return Err(StartError::NotReady(not_ready));
+        }
+        Ok(())
+    }
+
+    /// A new game for the same table: every player still holding a seat sits
+    /// down again with the same color and account, ready to `BEGIN`. Returns it
+    /// with each player's connection and new session token.
+    pub fn rematch(&self) -> (Game, Vec<(ClientId, String)>) {
+        let mut game = Game::new(self.rules.clone());
+        let mut tokens = Vec::new();
+        for name in self.standings() {
+            let player = &self.players[&name];
+            if player.client_id == ClientId::UNCLAIMED {
+                continue;
+            }
+            let Ok(token) = game.add_player(player.name.clone(), player.client_id) else {
+                continue;
+            };
+            game.set_player_color(&name, player.color.clone());
+            if let Some(seat) = game.players.get_mut(&name) {
+                seat.account_id = player.account_id;
+                seat.mark_as_fully_registered();
+                // Asking for the rematch was their way of saying they are ready.
+                seat.ready = true;
+            }
+            tokens.push((player.client_id, token));
+        }
+        if let Some(host) = self.host.clone().filter(|host| game.players.contains_key(host)) {
+            game.host = Some(host);
+        }
+        (game, tokens)
+    }
+
+    /// Moves the seat holding `token` to a new connection, returning the player's name
+    /// and the connection it had before.
+    pub fn resume_player(&mut self, token: &str, client_id: ClientId) -> Option<(String, ClientId)> {
+        let (name, player) = self.players.iter_mut().find(|(_, player)| player.session_token == token)?;
+        let previous = std::mem::replace(&mut player.client_id, client_id);
+        Some((name.clone(), previous))
+    }
+
+
+    pub fn set_player_color(&mut self, name: &str, color: Color) {
+        let name = player_id(name);
+        if let Some(player) = self.players.get_mut(&name) {
+            self.events.push(GameEvent::ColorChosen { player: name, color: format!("{:?}", color) });
+            player.set_color(color);
+            player.ready = false;
+        }
+    }
+
+    /// Gives a seat of a loaded game back to the player of that name, returning
+    /// its session token, if nobody has claimed it yet. Seats left empty in a
+    /// finished game are not handed out again.
+    pub fn claim_seat(&mut self, name: &str, client_id: ClientId) -> Option<String> {
+        if self.status == GameStatus::GameOver {
+            return None;
+        }
+        let player = self.players.get_mut(&player_id(name))?;
+        if player.client_id != ClientId::UNCLAIMED {
+            return None;
+        }
+        player.client_id = client_id;
+        Some(player.session_token.clone())
+    }
+
+    pub fn unclaimed_seats(&self) -> Vec<String> {
+        let mut names: Vec<String> = self.players.iter()
+            .filter(|(_, player)| player.client_id == ClientId::UNCLAIMED)
+            .map(|(name, _)| name.clone())
+            .collect();
+        names.sort();
+        names
+    }
+
+    pub fn get_player(&self, name: &str) -> Option<&Player> {
+        self.players.get(&player_id(name))
+    }
+
+
+    pub fn start_game(&mut self) {
+        if self.status == GameStatus::WaitingForPlayers && self.has_enough_players() {
+            self.status = GameStatus::InProgress;
+
+            if let Some(commitment) = self.dice.commitment() {
+                self.events.push(GameEvent::Committed { commitment });
+            }
+            let seats = self.seat_order();
+            let first = seats[self.dice.pick(seats.len())].clone();
+            self.events.push(GameEvent::Started { first: first.clone() });
+            self.current_turn = Some(first);
+        }
+    }
+
+    pub fn get_last_dice_roll(&self, player_name: &str) -> Option<u8> {
+        self.last_dice_roll.get(&player_id(player_name)).cloned()
+    }
+
+    /// The current player's roll while it still has to be played.
+    pub fn pending_roll(&self) -> Option<u8> {
+        self.pending_roll
+    }
+
+    pub fn get_player_mut(&mut self, name: &str) -> Option<&mut Player> {
+        self.players.get_mut(&player_id(name))
+    }
+
+    pub fn iter_players(&self) -> impl Iterator<Item = (&String, &Player)> {
+        self.players.iter()
+    }
+
+    pub fn available_colors(&self) -> Vec<Color> {
+        let all_colors = vec![Color::Red, Color::Green, Color::Blue, Color::Yellow];
+        all_colors.into_iter().filter(|color| {
+            !self.players.values().any(|player| player.color == *color)
+        }).collect()
+    }
+
+    pub fn can_add_player(&self) -> bool {
+        self.status == GameStatus::WaitingForPlayers && self.players.len() < self.rules.max_players
+    }
+
+    pub fn has_enough_players(&self) -> bool {
+        self.players.len() >= self.rules.min_players
+    }
+
+    pub fn has_client(&self, client: ClientId) -> bool {
+        self.get_player_by_client(client).is_some()
+    }
+
+    pub fn get_player_by_client(&self, client: ClientId) -> Option<(&String, &Player)> {
+        self.players.iter().find(|(_, player)| player.client_id == client)
+    }
+
+    pub fn get_player_by_client_mut(&mut self, client: ClientId) -> Option<&mut Player> {
+        self.players.values_mut().find(|player| player.client_id == client)
+    }
+
+    pub fn get_turn_order_message(&self) -> String {
+        if let Some(current_turn) = &self.current_turn {
+            let mut message = String::from("Turn Order:\n");
+            for name in self.seat_order() {
+                if name == *current_turn {
+                    message.push_str(&format!("-> {} (starts)\n", name));
+                } else {
+                    message.push_str(&format!("-> {}\n", name));
+                }
+            }
+            message
+        } else {
+            String::from("No turn order set.")
+        }
+    }
+
+    pub fn get_pawn_positions_message(&self) -> String {
+        self.players.iter().map(|(name, player)| {
+            let color = format!("{:?}", player.color);
+            format!("{} ({}): {}", name, color, player.get_pawn_positions())
+        }).collect::<Vec<_>>().join("\n")
+    }
+
+    /// Everything a client needs to catch up with a game already underway.
+    pub fn get_snapshot_message(&self) -> String {
+        let status = match self.status {
+            GameStatus::WaitingForPlayers => "waiting for players",
+            GameStatus::InProgress => "in progress",
+            GameStatus::GameOver => "over",
+        };
+
+        let mut message = format!("Game is {}.\n", status);
+        if self.players.is_empty() {
+            message.push_str("No players have joined yet.\n");
+        } else {
+            message.push_str(&self.get_pawn_positions_message());
+            message.push('\n');
+        }
+        if let (GameStatus::InProgress, Some(current_turn)) = (&self.status, &self.current_turn) {
+            message.push_str(&format!("It's {}'s turn.\n", current_turn));
+        }
+        message
+    }
+
+    /// Machine-readable summary of the game, broadcast to clients whenever it changes:
+    /// `STATE status=<s> turn=<name|-> dice=<n|-> exit=<n> players=<name>:<Color>:<pawns>;...`
+    /// where each pawn is `H` (house), `G` (goal) or its board position.
+    pub fn get_state_line(&self) -> String {
+        let status = match self.status {
+            GameStatus::WaitingForPlayers => "waiting",
+            GameStatus::InProgress => "playing",
+            GameStatus::GameOver => "over",
+        };
+        let turn = self.current_turn.clone().unwrap_or_else(|| "-".to_string());
+        let dice = self.pending_roll.map_or_else(|| "-".to_string(), |value| value.to_string());
+        let players = self.players.iter().map(|(name, player)| {
+            format!("{}:{:?}:{}", name, player.color, player.get_pawn_codes())
+        }).collect::<Vec<_>>().join(";");
+
+        format!("STATE status={} turn={} dice={} exit={} players={}", status, turn, dice, self.rules.exit_roll, players)
+    }
+
+    /// Players from first to last place: those who got every pawn home in the
+    /// order they did, then everyone else by how far their pawns have come.
+    pub fn standings(&self) -> Vec<String> {
+        let mut names: Vec<&String> = self.players.keys().filter(|name| !self.placements.contains(name)).collect();
+        names.sort_by_key(|name| (std::cmp::Reverse(self.players[*name].progress()), (*name).clone()));
+        self.placements.iter().chain(names).cloned().collect()
+    }
+
+    pub fn get_standings_message(&self) -> String {
+        let mut message = String::from("Final standings:\n");
+        for (index, name) in self.standings().iter().enumerate() {
+            message.push_str(&format!("{}. {}\n", index + 1, name));
+        }
+        message
+    }
+
+    pub fn get_players_mut(&mut self) -> &mut HashMap<String, Player> {
+        &mut self.players
+    }
+
+    pub fn is_in_progress(&self) -> bool {
+        self.status == GameStatus::InProgress
+    }
+
+    pub fn is_over(&self) -> bool {
+        self.status == GameStatus::GameOver
+    }
+
+    pub fn set_status(&mut self, new_status: GameStatus) {
+        self.status = new_status;
+    }
+
+    pub fn get_current_turn(&self) -> Option<&String> {
+        self.current_turn.as_ref()
+    }
+
+    pub fn num_players(&self) -> usize {
+        self.players.len()
+    }
+
+    pub fn set_last_dice_roll(&mut self, player_name: &str, value: u8) {
+        let player = player_id(player_name);
+        self.last_dice_roll.insert(player.clone(), value);
+        self.dice_histograms.entry(player.clone()).or_default().record(value);
+        self.pending_roll = Some(value);
+        self.events.push(GameEvent::Rolled { player, value });
+    }
+
+    /// Passes the turn to the next player who still has pawns to bring home.
+    pub fn next_turn(&mut self) {
+        self.pending_roll = None;
+        let player_names = self.seat_order();
+        if let Some(current_turn) = &self.current_turn {
+            let current_index = player_names.iter().position(|name| name == current_turn).unwrap_or(0);
+            self.current_turn = (1..=player_names.len())
+                .map(|offset| &player_names[(current_index + offset) % player_names.len()])
+                .find(|name| !self.placements.contains(name))
+                .cloned();
+        }
+        if let Some(player) = self.current_turn.clone() {
+            self.events.push(GameEvent::Turn { player });
+        }
+    }
+
+    /// Rolls the game's dice for `player` and plays the roll like `roll_dice`.
+    pub fn roll(&mut self, player: &str) -> Result<(u8, RollOutcome), TurnError> {
+        let player = &player_id(player);
+        self.check_turn(player)?;
+        if let Some(pending) = self.pending_roll {
+            return Err(TurnError::AlreadyRolled(pending));
+        }
+        let awaiting = self.awaiting_entropy();
+        if !awaiting.is_empty() {
+            return Err(TurnError::AwaitingEntropy(awaiting));
+        }
+        let value = self.dice.roll();
+        Ok((value, self.roll_dice(player, value)?))
+    }
+
+    /// Records `value` as `player`'s roll and works out what it allows.
+    /// A pawn is moved automatically when it is the only possible move, and the
+    /// turn passes when nothing can be moved.
+    pub fn roll_dice(&mut self, player: &str, value: u8) -> Result<RollOutcome, TurnError> {
+        let player = &player_id(player);
+        self.check_turn(player)?;
+        if let Some(pending) = self.pending_roll {
+            return Err(TurnError::AlreadyRolled(pending));
+        }
+        self.set_last_dice_roll(player, value);
+
+        let exit_roll = self.rules.exit_roll;
+        let (in_house, on_board) = self.players[player].get_pawn_counts();
+        let can_move_out = in_house > 0 && value == exit_roll;
+
+        let outcome = match (on_board, can_move_out) {
+            (0, true) => RollOutcome::MustMoveOut,
+            (0, false) => {
+                self.next_turn();
+                RollOutcome::NoMove
+            }
+            (_, true) => RollOutcome::MoveOrMoveOut,
+            (1, false) => {
+                let pawn = self.players[player].first_pawn_on_board_number().unwrap_or(1);
+                RollOutcome::AutoMoved(self.move_pawn(player, pawn)?)
+            }
+            _ => RollOutcome::ChoosePawn,
+        };
+        Ok(outcome)
+    }
+
+    /// Takes one of `player`'s pawns out of the house with the exit roll and passes the turn.
+    pub fn move_pawn_out(&mut self, player: &str) -> Result<(), TurnError> {
+        let player = &player_id(player);
+        self.check_turn(player)?;
+        let (in_house, _) = self.players[player].get_pawn_counts();
+        if self.pending_roll != Some(self.rules.exit_roll) || in_house == 0 {
+            return Err(TurnError::CannotMoveOut);
+        }
+
+        if let Some(pawn) = self.players.get_mut(player).and_then(Player::move_pawn_out) {
+            self.events.push(GameEvent::MovedOut { player: player.to_string(), pawn });
+        }
+        self.next_turn();
+        Ok(())
+    }
+
+    /// Moves one of `player`'s pawns by the pending roll. The turn passes unless
+    /// the move ends the game: when the first player gets every pawn home, or
+    /// with `play_to_last`, when only one player is left on the board.
+    pub fn move_pawn(&mut self, player: &str, pawn: usize) -> Result<MoveOutcome, TurnError> {
+        let player = &player_id(player);
+        self.check_turn(player)?;
+        let dice_value = self.pending_roll.ok_or(TurnError::NoRoll)?;
+        let current = self.players.get_mut(player).ok_or(TurnError::NotStarted)?;
+        if !current.is_valid_pawn_number(pawn) {
+            return Err(TurnError::InvalidPawn);
+        }
+
+        current.move_pawn(pawn, dice_value);
+        let to = current.pawns[pawn - 1].position.code();
+        let reached_goal = current.is_pawn_in_goal(pawn);
+        let finished = current.all_pawns_in_goal();
+        self.events.push(GameEvent::Moved { player: player.to_string(), pawn, to });
+
+        let place = finished.then(|| self.finish(player));
+        let game_over = place.is_some() && (!self.rules.play_to_last || self.placements.len() + 1 >= self.players.len());
+        if game_over {
+            if self.rules.play_to_last {
+                // The one player left takes the last place.
+                if let Some(last) = self.standings().get(self.placements.len()).cloned() {
+                    self.finish(&last);
+                }
+            }
+            self.events.push(GameEvent::Won { player: self.placements[0].clone() });
+            if let Some(secret) = self.dice.secret() {
+                self.events.push(GameEvent::Revealed { secret });
+            }
+            self.pending_roll = None;
+            self.status = GameStatus::GameOver;
+        } else {
+            self.next_turn();
+        }
+        Ok(MoveOutcome { pawn, reached_goal, place, game_over })
+    }
+
+    /// Gives `player` the next place, returning it.
+    fn finish(&mut self, player: &str) -> usize {
+        self.placements.push(player.to_string());
+        let place = self.placements.len();
+        if self.rules.play_to_last {
+            self.events.push(GameEvent::Finished { player: player.to_string(), place });
+        }
+        place
+    }
+
+    fn check_turn(&self, player: &str) -> Result<(), TurnError> {
+        let current = match (&self.status, &self.current_turn) {
+            (GameStatus::InProgress, Some(current)) => current,
+            (GameStatus::GameOver, _) => return Err(TurnError::GameOver),
+            _ => return Err(TurnError::NotStarted),
+        };
+        if current != player {
+            return Err(TurnError::NotYourTurn(current.clone()));
+        }
+        let unclaimed = self.unclaimed_seats();
+        if !unclaimed.is_empty() {
+            return Err(TurnError::SeatsUnclaimed(unclaimed));
+        }
+        Ok(())
+    }
+}
+
+const SAVE_HEADER: &str = "PARCHIS_SAVE";
+
+/// Saved games are plain text, one record per line:
+///
+/// ```text
+/// PARCHIS_SAVE
+/// rules <min players> <max players> <pawns per player> <exit roll> [<play to last>]
+/// status <waiting|playing|over>
+/// turn <name|->
+/// dice <pending roll|->
+/// seed <dice seed>
+/// fair_dice <secret>
+/// player <name> <color> <session token> <last roll|-> <pawn codes> [<account id>]
+/// event <event>
+/// ```
+///
+/// Only games with seeded dice have a `seed` line and only games with fair dice
+/// a `fair_dice` line. Loading replays the logged rolls on them, so the dice
+/// carry on where they left off.
+/// Loaded seats are unclaimed until their players come back with `JOIN` or `RESUME`.
+impl Game {
+    pub fn save(&self, path: &Path) -> io::Result<()> {
+        fs::write(path, self.to_save())
+    }
+
+    pub fn load(path: &Path) -> io::Result<Game> {
+        let contents = fs::read_to_string(path)?;
+        Game::from_save(&contents).map_err(|reason| io::Error::new(io::ErrorKind::InvalidData, reason))
+    }
+
+    pub fn to_save(&self) -> String {
+        let status = match self.status {
+            GameStatus::WaitingForPlayers => "waiting",
+            GameStatus::InProgress => "playing",
+            GameStatus::GameOver => "over",
+        };
+        let rules = &self.rules;
+        let mut contents = format!("{}\n", SAVE_HEADER);
+        contents.push_str(&format!("rules {} {} {} {} {}\n", rules.min_players, rules.max_players, rules.pawns_per_player, rules.exit_roll, rules.play_to_last));
+        contents.push_str(&format!("status {}\n", status));
+        contents.push_str(&format!("turn {}\n", self.current_turn.as_deref().unwrap_or("-")));
+        contents.push_str(&format!("dice {}\n", self.pending_roll.map_or_else(|| "-".to_string(), |value| value.to_string())));
+        if let Some(seed) = self.dice.seed() {
+            contents.push_str(&format!("seed {}\n", seed));
+        }
+        if let Some(secret) = self.dice.secret() {
+            contents.push_str(&format!("fair_dice {}\n", secret));
+        }
+
+        let mut names: Vec<&String> = self.players.keys().collect();
+        names.sort();
+        for name in names {
+            let player = &self.players[name];
+            let last_roll = self.last_dice_roll.get(name).map_or_else(|| "-".to_string(), |value| value.to_string());
+            let account = player.account_id.map_or_else(String::new, |id| format!(" {}", id));
+            contents.push_str(&format!("player {} {:?} {} {} {}{}\n",
+                player.name, player.color, player.session_token, last_roll, player.get_pawn_codes(), account));
+        }
+        for event in &self.events {
+            contents.push_str(&format!("event {}\n", event));
+        }
+        contents
+    }
+
+    pub fn from_save(contents: &str) -> Result<Game, String> {
+        let mut lines = contents.lines();
+        if lines.next().map(str::trim) != Some(SAVE_HEADER) {
+            return Err("not a parchis saved game".to_string());
+        }
+
+        let mut game = Game::new(Rules::default());
+        for (index, line) in lines.enumerate() {
+            let invalid = || format!("line {}: invalid record '{}'", index + 2, line);
+            let (kind, rest) = line.split_once(' ').unwrap_or((line, ""));
+            let fields: Vec<&str> = rest.split_whitespace().collect();
+
+            match (kind, fields.as_slice()) {
+                ("rules", [min, max, pawns, exit, play_to_last @ ..]) if play_to_last.len() <= 1 => {
+                    game.rules = Rules {
+                        min_players: min.parse().map_err(|_| invalid())?,
+                        max_players: max.parse().map_err(|_| invalid())?,
+                        pawns_per_player: pawns.parse().map_err(|_| invalid())?,
+                        exit_roll: exit.parse().map_err(|_| invalid())?,
+                        play_to_last: match play_to_last.first() {
+                            Some(value) => value.parse().map_err(|_| invalid())?,
+                            None => false,
+                        },
+                    };
+                }
+                ("status", [status]) => {
+                    game.status = match *status {
+                        "waiting" => GameStatus::WaitingForPlayers,
+                        "playing" => GameStatus::InProgress,
+                        "over" => GameStatus::GameOver,
+                        _ => return Err(invalid()),
+                    };
+                }
+                ("turn", [turn]) => game.current_turn = (*turn != "-").then(|| turn.to_string()),
+                ("dice", [dice]) => game.pending_roll = if *dice == "-" { None } else { Some(dice.parse().map_err(|_| invalid())?) },
+                ("seed", [seed]) => game.dice = Box::new(SeededDice::new(seed.parse().map_err(|_| invalid())?)),
+                ("fair_dice", [secret]) => game.dice = Box::new(FairDice::from_secret(secret).ok_or_else(invalid)?),
+                ("player", [name, color, token, last_roll, pawns, account @ ..]) if account.len() <= 1 => {
+                    let account_id = match account.first() {
+                        Some(id) => Some(id.parse().map_err(|_| invalid())?),
+                        None => None,
+                    };
+                    let color = Color::from_name(color).ok_or_else(invalid)?;
+                    let pawns = pawns.split(',')
+                        .map(|code| PawnPosition::parse(code).map(|position| Pawn { position }))
+                        .collect::<Option<Vec<_>>>()
+                        .ok_or_else(invalid)?;
+                    if *last_roll != "-" {
+                        game.last_dice_roll.insert(player_id(name), last_roll.parse().map_err(|_| invalid())?);
+                    }
+                    game.players.insert(player_id(name), Player {
+                        name: name.to_string(),
+                        pawns,
+                        is_fully_registered: color != Color::Unassigned,
+                        color,
+                        client_id: ClientId::UNCLAIMED,
+                        account_id,
+                        session_token: token.to_string(),
+                        ready: false,
+                    });
+                }
+                ("event", _) => {
+                    let event = GameEvent::parse(rest).ok_or_else(invalid)?;
+                    if let GameEvent::Rolled { player, value } = &event {
+                        game.dice_histograms.entry(player.clone()).or_default().record(*value);
+                    }
+                    game.record_placement(&event);
+                    game.replay_dice(&event);
+                    game.events.push(event);
+                }
+                ("", []) => {}
+                _ => return Err(invalid()),
+            }
+        }
+        Ok(game)
+    }
+
+    /// Replays a logged event on top of the current state, e.g. from a journal
+    /// written after the last save. Players seated this way get a fresh session token.
+    pub fn apply_event(&mut self, event: GameEvent) -> Result<(), String> {
+        let missing = |player: &str| format!("unknown player '{}' in '{}'", player, event);
+        match &event {
+            GameEvent::Joined { player } => {
+                self.add_player(player.clone(), ClientId::UNCLAIMED).map_err(|e| format!("cannot seat '{}': {}", player, e))?;
+                // add_player logged the join already.
+                return Ok(());
+            }
+            GameEvent::Left { player } => {
+                if !self.remove_player(player) {
+                    return Err(missing(player));
+                }
+                return Ok(());
+            }
+            GameEvent::ColorChosen { player, color } => {
+                let seat = self.players.get_mut(player).ok_or_else(|| missing(player))?;
+                seat.color = Color::from_name(color).ok_or_else(|| format!("bad color in '{}'", event))?;
+                seat.is_fully_registered = true;
+            }
+            GameEvent::Started { first } => {
+                self.status = GameStatus::InProgress;
+                self.current_turn = Some(first.clone());
+            }
+            GameEvent::Turn { player } => {
+                self.current_turn = Some(player.clone());
+                self.pending_roll = None;
+            }
+            GameEvent::Rolled { player, value } => {
+                self.last_dice_roll.insert(player.clone(), *value);
+                self.dice_histograms.entry(player.clone()).or_default().record(*value);
+                self.pending_roll = Some(*value);
+            }
+            GameEvent::MovedOut { player, pawn } | GameEvent::Moved { player, pawn, .. } => {
+                let position = match &event {
+                    GameEvent::Moved { to, .. } => PawnPosition::parse(to).ok_or_else(|| format!("bad position in '{}'", event))?,
+                    _ => PawnPosition::Board(1),
+                };
+                let seat = self.players.get_mut(player).ok_or_else(|| missing(player))?;
+                let slot = pawn.checked_sub(1).and_then(|index| seat.pawns.get_mut(index))
+                    .ok_or_else(|| format!("bad pawn in '{}'", event))?;
+                slot.position = position;
+            }
+            GameEvent::Committed { .. } | GameEvent::Entropy { .. } | GameEvent::Revealed { .. } | GameEvent::Finished { .. } => {}
+            GameEvent::Won { .. } => {
+                self.status = GameStatus::GameOver;
+                self.pending_roll = None;
+            }
+        }
+        self.record_placement(&event);
+        self.replay_dice(&event);
+        self.events.push(event);
+        Ok(())
+    }
+
+    /// Moves the dice past a logged event: seeded dice draw again what they
+    /// drew for the first player and for each roll.
+    fn replay_dice(&mut self, event: &GameEvent) {
+        match event {
+            GameEvent::Started { .. } => {
+                self.dice.pick(self.players.len());
+            }
+            GameEvent::Rolled { .. } => {
+                self.dice.roll();
+            }
+            _ => self.dice.observe(event),
+        }
+    }
+
+    /// Keeps `placements` in step with logged events. Games that end at the
+    /// first winner only log `WIN`.
+    fn record_placement(&mut self, event: &GameEvent) {
+        match event {
+            GameEvent::Finished { player, .. } => self.placements.push(player.clone()),
+            GameEvent::Won { player } if self.placements.is_empty() => self.placements.push(player.clone()),
+            _ => {}
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::dice::ScriptedDice;
+
+    fn started_game(dice: Box<dyn DiceSource>) -> Game {
+        let mut game = Game::new(Rules::default());
+        game.set_dice(dice);
+        for (name, color) in [("ana", Color::Red), ("bob", Color::Blue)] {
+            game.add_player(name.to_string(), ClientId(1)).unwrap();
+            game.set_player_color(name, color);
+            game.set_ready(name, true);
+        }
+        game.start_game();
+        game
+    }
+
+    /// Loads a saved game with both players back in their seats.
+    fn reload(saved: &str) -> Game {
+        let mut game = Game::from_save(saved).unwrap();
+        for name in ["ana", "bob"] {
+            game.claim_seat(name, ClientId(1)).unwrap();
+        }
+        game
+    }
+
+    /// Plays `turns` rolls, always taking a pawn out when allowed and otherwise
+    /// moving the first pawn that can move. Returns the rolls.
+    fn play(game: &mut Game, turns: usize) -> Vec<u8> {
+        let mut rolls = Vec::new();
+        for _ in 0..turns {
+            let player = game.get_current_turn().unwrap().clone();
+            let (value, outcome) = game.roll(&player).unwrap();
+            rolls.push(value);
+            match outcome {
+                RollOutcome::MustMoveOut | RollOutcome::MoveOrMoveOut => game.move_pawn_out(&player).unwrap(),
+                RollOutcome::ChoosePawn => {
+                    (1..=game.rules().pawns_per_player).find(|&pawn| game.move_pawn(&player, pawn).is_ok()).unwrap();
+                }
+                RollOutcome::AutoMoved(_) | RollOutcome::NoMove => {}
+            }
+        }
+        rolls
+    }
+
+    #[test]
+    fn valid_names() {
+        assert_eq!(validate_name("Ana_2-b"), Ok(()));
+        assert_eq!(validate_name("Begoña"), Ok(()));
+        assert_eq!(validate_name(&"a".repeat(MAX_NAME_LENGTH)), Ok(()));
+    }
+
+    #[test]
+    fn invalid_names() {
+        assert_eq!(validate_name(""), Err(NameError::Empty));
+        assert_eq!(validate_name(&"a".repeat(MAX_NAME_LENGTH + 1)), Err(NameError::TooLong));
+        assert_eq!(validate_name("ana bob"), Err(NameError::InvalidCharacters));
+        assert_eq!(validate_name("ana;bob"), Err(NameError::InvalidCharacters));
+    }
+
+    #[test]
+    fn names_are_unique_whatever_their_case() {
+        let mut game = Game::new(Rules::default());
+        game.add_player("Ana".to_string(), ClientId(1)).unwrap();
+        assert_eq!(game.add_player("ANA".to_string(), ClientId(2)), Err(NameError::Taken("Ana".to_string())));
+    }
+
+    #[test]
+    fn empty_seats_of_a_finished_game_cannot_be_claimed() {
+        let mut game = started_game(Box::new(SeededDice::new(7)));
+        game.get_player_mut("ana").unwrap().client_id = ClientId::UNCLAIMED;
+        assert!(game.claim_seat("ana", ClientId(2)).is_some());
+
+        game.get_player_mut("ana").unwrap().client_id = ClientId::UNCLAIMED;
+        game.set_status(GameStatus::GameOver);
+        assert_eq!(game.claim_seat("ana", ClientId(2)), None);
+    }
+
+    #[test]
+    fn the_host_role_passes_to_whoever_is_still_seated() {
+        let mut game = started_game(Box::new(SeededDice::new(7)));
+        assert_eq!(game.replace_absent_host(), None);
+
+        game.get_player_mut("ana").unwrap().client_id = ClientId::UNCLAIMED;
+        assert_eq!(game.replace_absent_host(), Some("bob".to_string()));
+        assert_eq!(game.host().map(String::as_str), Some("bob"));
+
+        game.get_player_mut("bob").unwrap().client_id = ClientId::UNCLAIMED;
+        assert_eq!(game.replace_absent_host(), None);
+    }
+
+    #[test]
+    fn dice_that_rolled_without_entropy_keep_rolling_after_a_load() {
+        let mut game = started_game(Box::new(FairDice::new()));
+        game.add_entropy("ana", "abc").unwrap();
+        assert_eq!(game.close_entropy(), vec!["bob".to_string()]);
+        play(&mut game, 3);
+
+        let mut loaded = reload(&game.to_save());
+        assert!(loaded.awaiting_entropy().is_empty());
+        assert_eq!(play(&mut loaded, 5), play(&mut game, 5));
+    }
+
+    #[test]
+    fn save_round_trip() {
+        let mut game = started_game(Box::new(SeededDice::new(7)));
+        play(&mut game, 20);
+
+        let saved = game.to_save();
+        let loaded = Game::from_save(&saved).unwrap();
+        assert_eq!(loaded.to_save(), saved);
+        assert_eq!(loaded.get_current_turn(), game.get_current_turn());
+        assert_eq!(loaded.dice_histogram("ana"), game.dice_histogram("ana"));
+    }
+
+    #[test]
+    fn loaded_seeded_dice_carry_on() {
+        let mut game = started_game(Box::new(SeededDice::new(7)));
+        play(&mut game, 20);
+        let mut loaded = reload(&game.to_save());
+
+        assert_eq!(play(&mut loaded, 20), play(&mut game, 20));
+    }
+
+    #[test]
+    fn journaled_seeded_dice_carry_on() {
+        let mut game = started_game(Box::new(SeededDice::new(7)));
+        let snapshot = game.to_save();
+        play(&mut game, 20);
+
+        let mut restored = reload(&snapshot);
+        for event in &game.events()[restored.events().len()..] {
+            restored.apply_event(event.clone()).unwrap();
+        }
+        assert_eq!(play(&mut restored, 20), play(&mut game, 20));
+    }
+
+    #[test]
+    fn loaded_fair_dice_carry_on() {
+        let mut game = started_game(Box::new(FairDice::new()));
+        game.add_entropy("ana", "abc").unwrap();
+        game.add_entropy("bob", "xyz").unwrap();
+        play(&mut game, 20);
+        let mut loaded = reload(&game.to_save());
+
+        assert_eq!(play(&mut loaded, 20), play(&mut game, 20));
+    }
+
+    #[test]
+    fn play_to_last_decides_every_place_and_skips_finished_players() {
+        let rules = Rules { pawns_per_player: 1, play_to_last: true, ..Rules::default() };
+        let mut game = Game::new(rules);
+        game.set_dice(Box::new(ScriptedDice::new(vec![6])));
+        for (name, color) in [("ana", Color::Red), ("bob", Color::Blue), ("cam", Color::Green)] {
+            game.add_player(name.to_string(), ClientId(1)).unwrap();
+            game.set_player_color(name, color);
+            game.set_ready(name, true);
+        }
+        game.start_game();
+
+        let mut turns_after_ana_finished = Vec::new();
+        while !game.is_over() {
+            let player = game.get_current_turn().unwrap().clone();
+            if game.get_player("ana").unwrap().all_pawns_in_goal() {
+                turns_after_ana_finished.push(player);
+            }
+            play(&mut game, 1);
+        }
+
+        assert_eq!(game.standings(), ["ana", "bob", "cam"]);
+        assert!(!turns_after_ana_finished.is_empty());
+        assert!(!turns_after_ana_finished.contains(&"ana".to_string()));
+        let places: Vec<_> = game.events().iter().filter_map(|event| match event {
+            GameEvent::Finished { player, place } => Some((player.as_str(), *place)),
+            _ => None,
+        }).collect();
+        assert_eq!(places, [("ana", 1), ("bob", 2), ("cam", 3)]);
+    }
+
+    #[test]
+    fn without_play_to_last_the_game_ends_with_the_first_finisher() {
+        let mut game = started_game(Box::new(ScriptedDice::new(vec![6])));
+        while !game.is_over() {
+            play(&mut game, 1);
+        }
+
+        assert_eq!(game.standings(), ["ana", "bob"]);
+        assert!(game.get_player("ana").unwrap().all_pawns_in_goal());
+        assert!(!game.get_player("bob").unwrap().all_pawns_in_goal());
+        assert!(!game.events().iter().any(|event| matches!(event, GameEvent::Finished { .. })));
+    }
+
+    #[test]
+    fn rematch_seats_everyone_still_at_the_table() {
+        let rules = Rules { pawns_per_player: 1, ..Rules::default() };
+        let mut game = Game::new(rules);
+        game.set_dice(Box::new(ScriptedDice::new(vec![6])));
+        for (client, (name, color)) in [("ana", Color::Red), ("bob", Color::Blue), ("cam", Color::Green)].into_iter().enumerate() {
+            game.add_player(name.to_string(), ClientId(client as u64 + 1)).unwrap();
+            game.set_player_color(name, color);
+            game.set_ready(name, true);
+        }
+        game.start_game();
+        while !game.is_over() {
+            play(&mut game, 1);
+        }
+        game.get_player_mut("bob").unwrap().client_id = ClientId::UNCLAIMED;
+        game.set_host("cam");
+
+        let (rematch, tokens) = game.rematch();
+
+        assert!(!rematch.is_in_progress() && !rematch.is_over());
+        assert!(rematch.get_player("bob").is_none());
+        assert_eq!(rematch.host().map(String::as_str), Some("cam"));
+        assert_eq!(tokens.len(), 2);
+        for (client, token) in &tokens {
+            let (name, player) = rematch.get_player_by_client(*client).unwrap();
+            assert_eq!(player.session_token(), token);
+            assert_eq!(player.color(), game.get_player(name).unwrap().color());
+            assert!(player.is_ready());
+        }
+        assert_eq!(rematch.check_start(), Ok(()));
+    }
+}
@@ -0,0 +1,6 @@
+//! Parchis rules shared by the server and the client's local mode.
+
+pub mod dice;
+pub mod events;
+pub mod game_state;
+pub mod rules;
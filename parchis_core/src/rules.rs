@@ -0,0 +1,145 @@
+use std::fmt;
+use std::fs;
+use std::io;
+use std::path::Path;
+
+#[derive(Debug)]
+pub enum RulesError {
+    Io(io::Error),
+    Invalid(String),
+}
+
+impl fmt::Display for RulesError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            RulesError::Io(e) => write!(f, "I/O error: {}", e),
+            RulesError::Invalid(reason) => write!(f, "{}", reason),
+        }
+    }
+}
+
+impl std::error::Error for RulesError {}
+
+impl From<io::Error> for RulesError {
+    fn from(e: io::Error) -> Self {
+        RulesError::Io(e)
+    }
+}
+
+/// Table rules that may be overridden with `--rules <file>`.
+///
+/// The file holds one `key = value` pair per line; blank lines and lines
+/// starting with `#` are ignored, and missing keys keep their default.
+#[derive(Clone, Debug)]
+pub struct Rules {
+    pub min_players: usize,
+    pub max_players: usize,
+    pub pawns_per_player: usize,
+    pub exit_roll: u8,
+    /// Keep playing after the first player finishes until only one is left,
+    /// so every place is decided.
+    pub play_to_last: bool,
+}
+
+impl Default for Rules {
+    fn default() -> Self {
+        Rules {
+            min_players: 2,
+            max_players: 4,
+            pawns_per_player: 4,
+            exit_roll: 6,
+            play_to_last: false,
+        }
+    }
+}
+
+impl Rules {
+    pub fn load(path: &Path) -> Result<Rules, RulesError> {
+        let contents = fs::read_to_string(path)?;
+        Rules::parse(&contents)
+    }
+
+    pub fn parse(contents: &str) -> Result<Rules, RulesError> {
+        let mut rules = Rules::default();
+
+        for (index, line) in contents.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+
+            let invalid = |reason: &str| RulesError::Invalid(format!("line {}: {}", index + 1, reason));
+            let (key, value) = line.split_once('=').ok_or_else(|| invalid("expected 'key = value'"))?;
+            let value = value.trim();
+
+            match key.trim() {
+                "min_players" => rules.min_players = value.parse().map_err(|_| invalid("min_players must be a number"))?,
+                "max_players" => rules.max_players = value.parse().map_err(|_| invalid("max_players must be a number"))?,
+                "pawns_per_player" => rules.pawns_per_player = value.parse().map_err(|_| invalid("pawns_per_player must be a number"))?,
+                "exit_roll" => rules.exit_roll = value.parse().map_err(|_| invalid("exit_roll must be a number"))?,
+                "play_to_last" => rules.play_to_last = value.parse().map_err(|_| invalid("play_to_last must be true or false"))?,
+                other => return Err(invalid(&format!("unknown rule '{}'", other))),
+            }
+        }
+
+        rules.validate()?;
+        Ok(rules)
+    }
+
+    fn validate(&self) -> Result<(), RulesError> {
+        if self.min_players < 2 || self.min_players > self.max_players {
+            return Err(RulesError::Invalid("min_players must be at least 2 and at most max_players".to_string()));
+        }
+        if self.max_players > 4 {
+            return Err(RulesError::Invalid("max_players cannot exceed the 4 available colors".to_string()));
+        }
+        if self.pawns_per_player == 0 || self.pawns_per_player > 4 {
+            return Err(RulesError::Invalid("pawns_per_player must be between 1 and 4".to_string()));
+        }
+        if !(1..=6).contains(&self.exit_roll) {
+            return Err(RulesError::Invalid("exit_roll must be a dice value between 1 and 6".to_string()));
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn error(contents: &str) -> String {
+        Rules::parse(contents).unwrap_err().to_string()
+    }
+
+    #[test]
+    fn missing_keys_keep_their_default() {
+        let rules = Rules::parse("# quick games\n\npawns_per_player = 2\n").unwrap();
+        assert_eq!(rules.pawns_per_player, 2);
+        assert_eq!(rules.min_players, 2);
+        assert_eq!(rules.max_players, 4);
+        assert_eq!(rules.exit_roll, 6);
+        assert!(!rules.play_to_last);
+    }
+
+    #[test]
+    fn every_key_can_be_set() {
+        let rules = Rules::parse("min_players=3\nmax_players = 3\npawns_per_player = 1\nexit_roll = 5\nplay_to_last = true").unwrap();
+        assert_eq!((rules.min_players, rules.max_players, rules.pawns_per_player, rules.exit_roll), (3, 3, 1, 5));
+        assert!(rules.play_to_last);
+    }
+
+    #[test]
+    fn bad_lines_are_reported_with_their_number() {
+        assert_eq!(error("exit_roll = 6\nspeed = 2"), "line 2: unknown rule 'speed'");
+        assert_eq!(error("exit_roll six"), "line 1: expected 'key = value'");
+        assert_eq!(error("min_players = two"), "line 1: min_players must be a number");
+    }
+
+    #[test]
+    fn impossible_tables_are_refused() {
+        assert_eq!(error("max_players = 5"), "max_players cannot exceed the 4 available colors");
+        assert_eq!(error("min_players = 3\nmax_players = 2"), "min_players must be at least 2 and at most max_players");
+        assert_eq!(error("pawns_per_player = 0"), "pawns_per_player must be between 1 and 4");
+        assert_eq!(error("exit_roll = 7"), "exit_roll must be a dice value between 1 and 6");
+    }
+}
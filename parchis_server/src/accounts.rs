@@ -0,0 +1,363 @@
+use std::fmt;
+use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
+use argon2::password_hash::rand_core::OsRng;
+use argon2::password_hash::{PasswordHash, PasswordHasher, PasswordVerifier, SaltString};
+use argon2::Argon2;
+use parchis_core::dice::DiceHistogram;
+use rusqlite::{params, Connection, OptionalExtension};
+use crate::ratings::{rating_changes, INITIAL_RATING};
+
+pub const MIN_PASSWORD_LENGTH: usize = 6;
+
+#[derive(Clone, Debug)]
+pub struct Account {
+    pub id: i64,
+    pub username: String,
+    /// Name used at the table; starts out as the username.
+    pub display_name: String,
+    pub preferred_color: Option<String>,
+}
+
+/// A finished game as it goes into the history.
+pub struct GameRecord {
+    pub room_id: usize,
+    pub replay: Option<String>,
+    pub duration_secs: u64,
+    pub rolls: usize,
+    /// Every seat, from first to last place.
+    pub seats: Vec<SeatRecord>,
+}
+
+pub struct SeatRecord {
+    /// `None` for guests, who are listed in the history but have no stats.
+    pub account_id: Option<i64>,
+    pub name: String,
+    pub sixes: usize,
+    /// The seat's rolls, face by face.
+    pub dice: DiceHistogram,
+}
+
+pub struct PlayerStats {
+    pub rating: f64,
+    pub games: u32,
+    pub wins: u32,
+    pub average_position: f64,
+    pub sixes: u32,
+    pub average_duration_secs: f64,
+}
+
+pub struct HistoryEntry {
+    pub game_id: i64,
+    pub finished_at: i64,
+    pub room_id: usize,
+    pub winner: String,
+    pub players: usize,
+    /// The requesting player's place, when the history is their own.
+    pub position: Option<usize>,
+    pub replay: Option<String>,
+}
+
+pub struct LeaderboardEntry {
+    pub display_name: String,
+    pub rating: f64,
+    pub games: u32,
+}
+
+#[derive(Debug)]
+pub enum AccountError {
+    Database(rusqlite::Error),
+    UsernameTaken,
+    WeakPassword,
+    InvalidCredentials,
+}
+
+impl fmt::Display for AccountError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            AccountError::Database(e) => write!(f, "account database error: {}", e),
+            AccountError::UsernameTaken => write!(f, "That username is already registered."),
+            AccountError::WeakPassword => write!(f, "Passwords must be at least {} characters long.", MIN_PASSWORD_LENGTH),
+            AccountError::InvalidCredentials => write!(f, "Unknown username or wrong password."),
+        }
+    }
+}
+
+impl std::error::Error for AccountError {}
+
+impl From<rusqlite::Error> for AccountError {
+    fn from(e: rusqlite::Error) -> Self {
+        AccountError::Database(e)
+    }
+}
+
+/// Registered players, kept in a SQLite file. Passwords are stored as salted Argon2 hashes.
+pub struct AccountStore {
+    db: Connection,
+    path: PathBuf,
+}
+
+impl AccountStore {
+    pub fn open(path: &Path) -> Result<AccountStore, AccountError> {
+        let db = Connection::open(path)?;
+        db.execute_batch(
+            "CREATE TABLE IF NOT EXISTS accounts (
+                id INTEGER PRIMARY KEY,
+                username TEXT NOT NULL UNIQUE COLLATE NOCASE,
+                password_hash TEXT NOT NULL,
+                display_name TEXT NOT NULL UNIQUE COLLATE NOCASE,
+                preferred_color TEXT
+            );
+            CREATE TABLE IF NOT EXISTS games (
+                id INTEGER PRIMARY KEY,
+                room INTEGER NOT NULL,
+                finished_at INTEGER NOT NULL,
+                duration_secs INTEGER NOT NULL,
+                rolls INTEGER NOT NULL,
+                replay TEXT
+            );
+            CREATE TABLE IF NOT EXISTS game_players (
+                game_id INTEGER NOT NULL REFERENCES games (id),
+                account_id INTEGER REFERENCES accounts (id),
+                name TEXT NOT NULL,
+                position INTEGER NOT NULL,
+                sixes INTEGER NOT NULL
+            );
+            CREATE TABLE IF NOT EXISTS dice_rolls (
+                game_id INTEGER NOT NULL REFERENCES games (id),
+                account_id INTEGER REFERENCES accounts (id),
+                face INTEGER NOT NULL,
+                count INTEGER NOT NULL
+            );
+            CREATE TABLE IF NOT EXISTS ratings (
+                account_id INTEGER PRIMARY KEY REFERENCES accounts (id),
+                rating REAL NOT NULL,
+                games INTEGER NOT NULL
+            );",
+        )?;
+        Ok(AccountStore { db, path: path.to_path_buf() })
+    }
+
+    /// A second connection to the same file, for work done off the game loop.
+    pub fn reopen(&self) -> Result<AccountStore, AccountError> {
+        AccountStore::open(&self.path)
+    }
+
+    pub fn register(&self, username: &str, password: &str) -> Result<Account, AccountError> {
+        if password.chars().count() < MIN_PASSWORD_LENGTH {
+            return Err(AccountError::WeakPassword);
+        }
+        if self.is_name_taken(username)? {
+            return Err(AccountError::UsernameTaken);
+        }
+
+        let salt = SaltString::generate(&mut OsRng);
+        let password_hash = Argon2::default()
+            .hash_password(password.as_bytes(), &salt)
+            .map_err(|_| AccountError::WeakPassword)?
+            .to_string();
+        self.db.execute(
+            "INSERT INTO accounts (username, password_hash, display_name) VALUES (?1, ?2, ?1)",
+            params![username, password_hash],
+        )?;
+
+        Ok(Account {
+            id: self.db.last_insert_rowid(),
+            username: username.to_string(),
+            display_name: username.to_string(),
+            preferred_color: None,
+        })
+    }
+
+    pub fn login(&self, username: &str, password: &str) -> Result<Account, AccountError> {
+        let row = self.db.query_row(
+            "SELECT id, username, password_hash, display_name, preferred_color FROM accounts WHERE username = ?1",
+            params![username],
+            |row| Ok((
+                Account { id: row.get(0)?, username: row.get(1)?, display_name: row.get(3)?, preferred_color: row.get(4)? },
+                row.get::<_, String>(2)?,
+            )),
+        ).optional()?;
+        let Some((account, password_hash)) = row else {
+            return Err(AccountError::InvalidCredentials);
+        };
+
+        let parsed = PasswordHash::new(&password_hash).map_err(|_| AccountError::InvalidCredentials)?;
+        Argon2::default()
+            .verify_password(password.as_bytes(), &parsed)
+            .map_err(|_| AccountError::InvalidCredentials)?;
+        Ok(account)
+    }
+
+    /// Whether `name` is some account's username or display name, and so off limits to guests.
+    pub fn is_name_taken(&self, name: &str) -> Result<bool, AccountError> {
+        let taken = self.db.query_row(
+            "SELECT EXISTS (SELECT 1 FROM accounts WHERE username = ?1 OR display_name = ?1)",
+            params![name],
+            |row| row.get(0),
+        )?;
+        Ok(taken)
+    }
+
+    pub fn set_display_name(&self, account: &mut Account, display_name: &str) -> Result<(), AccountError> {
+        let owner: Option<i64> = self.db.query_row(
+            "SELECT id FROM accounts WHERE username = ?1 OR display_name = ?1",
+            params![display_name],
+            |row| row.get(0),
+        ).optional()?;
+        if owner.is_some_and(|id| id != account.id) {
+            return Err(AccountError::UsernameTaken);
+        }
+
+        self.db.execute("UPDATE accounts SET display_name = ?1 WHERE id = ?2", params![display_name, account.id])?;
+        account.display_name = display_name.to_string();
+        Ok(())
+    }
+
+    pub fn set_preferred_color(&self, account: &mut Account, color: &str) -> Result<(), AccountError> {
+        self.db.execute("UPDATE accounts SET preferred_color = ?1 WHERE id = ?2", params![color, account.id])?;
+        account.preferred_color = Some(color.to_string());
+        Ok(())
+    }
+
+    /// Looks an account up by username or display name.
+    pub fn find_account(&self, name: &str) -> Result<Option<Account>, AccountError> {
+        let account = self.db.query_row(
+            "SELECT id, username, display_name, preferred_color FROM accounts WHERE username = ?1 OR display_name = ?1",
+            params![name],
+            |row| Ok(Account { id: row.get(0)?, username: row.get(1)?, display_name: row.get(2)?, preferred_color: row.get(3)? }),
+        ).optional()?;
+        Ok(account)
+    }
+
+    /// Stores a finished game and its seats, and updates the ratings of the
+    /// accounts that played it, all in one transaction.
+    ///
+    /// Only seats held by accounts are rated, and only against each other:
+    /// guests carry no rating, so a game with fewer than two accounts leaves
+    /// every rating as it was.
+    pub fn record_game(&mut self, record: &GameRecord) -> Result<i64, AccountError> {
+        let finished_at = SystemTime::now().duration_since(UNIX_EPOCH).map_or(0, |elapsed| elapsed.as_secs() as i64);
+        let transaction = self.db.transaction()?;
+        transaction.execute(
+            "INSERT INTO games (room, finished_at, duration_secs, rolls, replay) VALUES (?1, ?2, ?3, ?4, ?5)",
+            params![record.room_id as i64, finished_at, record.duration_secs as i64, record.rolls as i64, record.replay],
+        )?;
+        let game_id = transaction.last_insert_rowid();
+        for (index, seat) in record.seats.iter().enumerate() {
+            transaction.execute(
+                "INSERT INTO game_players (game_id, account_id, name, position, sixes)
+                 VALUES (?1, ?2, ?3, ?4, ?5)",
+                params![game_id, seat.account_id, seat.name, index as i64 + 1, seat.sixes as i64],
+            )?;
+            for face in (1..=6).filter(|&face| seat.dice.count(face) > 0) {
+                transaction.execute(
+                    "INSERT INTO dice_rolls (game_id, account_id, face, count) VALUES (?1, ?2, ?3, ?4)",
+                    params![game_id, seat.account_id, face, seat.dice.count(face) as i64],
+                )?;
+            }
+        }
+
+        let rated: Vec<i64> = record.seats.iter().filter_map(|seat| seat.account_id).collect();
+        if rated.len() >= 2 {
+            let mut ratings = Vec::with_capacity(rated.len());
+            for account_id in &rated {
+                let rating = transaction.query_row(
+                    "SELECT rating FROM ratings WHERE account_id = ?1",
+                    params![account_id],
+                    |row| row.get(0),
+                ).optional()?;
+                ratings.push(rating.unwrap_or(INITIAL_RATING));
+            }
+            for ((account_id, rating), change) in rated.iter().zip(&ratings).zip(rating_changes(&ratings)) {
+                transaction.execute(
+                    "INSERT INTO ratings (account_id, rating, games) VALUES (?1, ?2, 1)
+                     ON CONFLICT (account_id) DO UPDATE SET rating = excluded.rating, games = games + 1",
+                    params![account_id, rating + change],
+                )?;
+            }
+        }
+        transaction.commit()?;
+        Ok(game_id)
+    }
+
+    pub fn stats(&self, account_id: i64) -> Result<PlayerStats, AccountError> {
+        let stats = self.db.query_row(
+            "SELECT COUNT(*), COALESCE(SUM(p.position = 1), 0), COALESCE(AVG(p.position), 0),
+                    COALESCE(SUM(p.sixes), 0), COALESCE(AVG(g.duration_secs), 0)
+             FROM game_players p JOIN games g ON g.id = p.game_id
+             WHERE p.account_id = ?1",
+            params![account_id],
+            |row| Ok(PlayerStats {
+                rating: INITIAL_RATING,
+                games: row.get(0)?,
+                wins: row.get(1)?,
+                average_position: row.get(2)?,
+                sixes: row.get(3)?,
+                average_duration_secs: row.get(4)?,
+            }),
+        )?;
+        let rating = self.db.query_row(
+            "SELECT rating FROM ratings WHERE account_id = ?1",
+            params![account_id],
+            |row| row.get(0),
+        ).optional()?;
+        Ok(PlayerStats { rating: rating.unwrap_or(INITIAL_RATING), ..stats })
+    }
+
+    /// Rolls across every finished game, only those of `account_id` when given.
+    pub fn dice_histogram(&self, account_id: Option<i64>) -> Result<DiceHistogram, AccountError> {
+        let mut statement = self.db.prepare(
+            "SELECT face, SUM(count) FROM dice_rolls WHERE ?1 IS NULL OR account_id = ?1 GROUP BY face",
+        )?;
+        let rows = statement.query_map(params![account_id], |row| Ok((row.get::<_, i64>(0)?, row.get::<_, i64>(1)?)))?;
+        let mut counts = [0; 6];
+        for row in rows {
+            let (face, count) = row?;
+            if let Some(slot) = (face as usize).checked_sub(1).and_then(|index| counts.get_mut(index)) {
+                *slot = count as u64;
+            }
+        }
+        Ok(DiceHistogram::from_counts(counts))
+    }
+
+    /// The best rated accounts, highest first.
+    pub fn leaderboard(&self, limit: usize) -> Result<Vec<LeaderboardEntry>, AccountError> {
+        let mut statement = self.db.prepare(
+            "SELECT a.display_name, r.rating, r.games
+             FROM ratings r JOIN accounts a ON a.id = r.account_id
+             ORDER BY r.rating DESC, r.games DESC, a.display_name
+             LIMIT ?1",
+        )?;
+        let entries = statement.query_map(params![limit as i64], |row| Ok(LeaderboardEntry {
+            display_name: row.get(0)?,
+            rating: row.get(1)?,
+            games: row.get(2)?,
+        }))?;
+        Ok(entries.collect::<Result<Vec<_>, _>>()?)
+    }
+
+    /// The most recent games, only those `account_id` played in when given.
+    pub fn history(&self, account_id: Option<i64>, limit: usize) -> Result<Vec<HistoryEntry>, AccountError> {
+        let mut statement = self.db.prepare(
+            "SELECT g.id, g.finished_at, g.room, g.replay,
+                    (SELECT name FROM game_players WHERE game_id = g.id AND position = 1),
+                    (SELECT COUNT(*) FROM game_players WHERE game_id = g.id),
+                    (SELECT position FROM game_players WHERE game_id = g.id AND account_id = ?1)
+             FROM games g
+             WHERE ?1 IS NULL OR EXISTS (SELECT 1 FROM game_players WHERE game_id = g.id AND account_id = ?1)
+             ORDER BY g.finished_at DESC, g.id DESC
+             LIMIT ?2",
+        )?;
+        let entries = statement.query_map(params![account_id, limit as i64], |row| Ok(HistoryEntry {
+            game_id: row.get(0)?,
+            finished_at: row.get(1)?,
+            room_id: row.get::<_, i64>(2)? as usize,
+            replay: row.get(3)?,
+            winner: row.get::<_, Option<String>>(4)?.unwrap_or_default(),
+            players: row.get::<_, i64>(5)? as usize,
+            position: row.get::<_, Option<i64>>(6)?.map(|position| position as usize),
+        }))?;
+        Ok(entries.collect::<Result<Vec<_>, _>>()?)
+    }
+}
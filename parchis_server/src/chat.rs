@@ -0,0 +1,75 @@
+use std::collections::{HashMap, VecDeque};
+use std::time::{Duration, Instant};
+use crate::communication::ClientId;
+
+pub const MAX_CHAT_LENGTH: usize = 200;
+
+/// How many chat messages a client may send within `FLOOD_WINDOW`.
+const FLOOD_LIMIT: usize = 5;
+const FLOOD_WINDOW: Duration = Duration::from_secs(10);
+
+/// How long the same message cannot be sent again.
+const REPEAT_WINDOW: Duration = Duration::from_secs(30);
+
+const BLOCKED_WORDS: &[&str] = &["idiot", "stupid", "moron", "idiota", "estupido", "imbecil", "tonto"];
+
+/// Rate limits chat per client and masks blocked words.
+#[derive(Default)]
+pub struct ChatFilter {
+    recent: HashMap<ClientId, VecDeque<Instant>>,
+    last_message: HashMap<ClientId, (String, Instant)>,
+}
+
+impl ChatFilter {
+    pub fn new() -> Self {
+        ChatFilter::default()
+    }
+
+    /// Returns the text to deliver, or the reason it was rejected.
+    pub fn check(&mut self, client: ClientId, text: &str) -> Result<String, String> {
+        let text = text.trim();
+        if text.is_empty() {
+            return Err("Cannot send an empty message.".to_string());
+        }
+        if text.chars().count() > MAX_CHAT_LENGTH {
+            return Err(format!("Message too long (max {} characters).", MAX_CHAT_LENGTH));
+        }
+
+        let now = Instant::now();
+        let recent = self.recent.entry(client).or_default();
+        while recent.front().is_some_and(|sent| now.duration_since(*sent) > FLOOD_WINDOW) {
+            recent.pop_front();
+        }
+        if recent.len() >= FLOOD_LIMIT {
+            return Err("You are sending messages too fast. Please wait a moment.".to_string());
+        }
+        let repeated = self.last_message.get(&client)
+            .is_some_and(|(last, sent)| last.eq_ignore_ascii_case(text) && now.duration_since(*sent) <= REPEAT_WINDOW);
+        if repeated {
+            return Err("Please don't repeat the same message.".to_string());
+        }
+
+        recent.push_back(now);
+        self.last_message.insert(client, (text.to_string(), now));
+        Ok(censor(text))
+    }
+
+    pub fn forget(&mut self, client: ClientId) {
+        self.recent.remove(&client);
+        self.last_message.remove(&client);
+    }
+}
+
+fn censor(text: &str) -> String {
+    text.split(' ')
+        .map(|word| {
+            let bare = word.trim_matches(|c: char| !c.is_alphanumeric()).to_lowercase();
+            if BLOCKED_WORDS.contains(&bare.as_str()) {
+                "*".repeat(word.chars().count())
+            } else {
+                word.to_string()
+            }
+        })
+        .collect::<Vec<_>>()
+        .join(" ")
+}
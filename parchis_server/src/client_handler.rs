@@ -1,72 +1,92 @@
-use std::sync::MutexGuard;
-use std::net::TcpStream;
-use std::sync::{Arc, Mutex};
-use crate::game_state::Game;
-use crate::communication::broadcast_message;
-use std::io::{Read, Write};
-use rand::Rng;
-use crate::game_state::Color;
-use crate::game_state::GameStatus;
+use tokio::io::AsyncWriteExt;
+use tokio::net::tcp::{OwnedReadHalf, OwnedWriteHalf};
+use tokio::net::TcpStream;
+use tokio::sync::mpsc;
+use parchis_core::game_state::{player_id, Color, Game, GameStatus, MoveOutcome, RollOutcome};
+use crate::communication::{broadcast_message, send_message, ClientId, Clients, Frame, FrameReader, OUTBOUND_QUEUE_SIZE};
+use crate::error::{ServerError, ServerResult};
+use crate::game_loop::{ClientEvent, GameCommand};
 
 
 
 
-pub fn handle_client(mut stream: TcpStream, clients: Arc<Mutex<Vec<TcpStream>>>, game: Arc<Mutex<Game>>) {
-    let mut buffer = [0; 1024];
+pub async fn handle_client(stream: TcpStream, client: ClientId, commands: mpsc::Sender<GameCommand>) {
+    if let Err(e) = serve_client(stream, client, &commands).await {
+        eprintln!("Connection {} closed: {}", client, e);
+    }
 
-    let welcome_msg = "Welcome to Parchis! Enter 'JOIN <name>' to join the game.\nEND_OF_MESSAGE\n";
-    stream.write_all(welcome_msg.as_bytes()).expect("Failed to send welcome message");
-    stream.flush().expect("Failed to flush stream"); 
+    let _ = submit(&commands, client, ClientEvent::Disconnected).await;
+}
 
-    let clients_clone = Arc::clone(&clients);
+async fn serve_client(stream: TcpStream, client: ClientId, commands: &mpsc::Sender<GameCommand>) -> ServerResult<()> {
+    let (read_half, write_half) = stream.into_split();
+    let (outbound_tx, outbound_rx) = mpsc::channel(OUTBOUND_QUEUE_SIZE);
+    submit(commands, client, ClientEvent::Connected(outbound_tx)).await?;
 
-    {
-        let mut clients = clients.lock().unwrap();
-        clients.push(stream.try_clone().expect("Failed to clone stream"));
+    // Whichever side finishes first ends the connection: the peer hanging up,
+    // or the game task dropping our outbound queue.
+    tokio::select! {
+        result = read_commands(read_half, client, commands) => result,
+        result = write_outbound(write_half, outbound_rx) => result,
     }
+}
 
-    loop {
-        match stream.read(&mut buffer) {
-            Ok(size) => {
-                if size == 0 {
-                    break;
-                }
+async fn read_commands(read_half: OwnedReadHalf, client: ClientId, commands: &mpsc::Sender<GameCommand>) -> ServerResult<()> {
+    let mut frames = FrameReader::new(read_half);
 
-                let message = String::from_utf8_lossy(&buffer[..size]).trim().to_string();
-                println!("Received message: {}", message);
+    while let Some(frame) = frames.next_frame().await? {
+        let event = match frame {
+            Frame::Line(message) => ClientEvent::Line(message),
+            Frame::TooLong => ClientEvent::LineTooLong,
+        };
+        submit(commands, client, event).await?;
+    }
 
-                handle_message(&message, &stream, &game, &clients_clone);
-            }
-            Err(e) => {
-                eprintln!("Error reading from stream: {}", e);
-                break;
-            }
-        }
+    Ok(())
+}
+
+async fn write_outbound(mut write_half: OwnedWriteHalf, mut outbound: mpsc::Receiver<String>) -> ServerResult<()> {
+    while let Some(message) = outbound.recv().await {
+        write_half.write_all(message.as_bytes()).await?;
     }
 
-    remove_client(&stream, &clients_clone);
+    Ok(())
+}
+
+async fn submit(commands: &mpsc::Sender<GameCommand>, client: ClientId, event: ClientEvent) -> ServerResult<()> {
+    commands.send(GameCommand { client, event }).await.map_err(|_| ServerError::GameUnavailable)
 }
 
 
 
-fn handle_message(message: &str, mut stream: &TcpStream, game: &Arc<Mutex<Game>>, clients: &Arc<Mutex<Vec<TcpStream>>>) {
+pub fn handle_message(message: &str, client: ClientId, game: &mut Game, clients: &mut Clients) -> ServerResult<()> {
     let command_parts: Vec<&str> = message.split_whitespace().collect();
-    match command_parts[0] {
+    let Some(command) = command_parts.first() else {
+        return Ok(());
+    };
+
+    match *command {
 
         "JOIN" => {
-            let mut game_guard = game.lock().unwrap();
-            if !game_guard.can_add_player() {
+            if !game.can_add_player() {
                 let response = "Game is full or already started.\nEND_OF_MESSAGE\n";
-                stream.write_all(response.as_bytes()).expect("Failed to write response");
-                return;
+                send_message(clients, client, response);
+                return Ok(());
             }
         
             if let Some(name) = command_parts.get(1) {
-                let player_addr = stream.peer_addr().expect("Failed to get peer address");
-                
-                game_guard.add_player(name.to_string(), player_addr);
+                let session_token = match game.add_player(name.to_string(), client) {
+                    Ok(session_token) => session_token,
+                    Err(e) => {
+                        send_message(clients, client, &format!("{}\nEND_OF_MESSAGE\n", e));
+                        return Ok(());
+                    }
+                };
+                if let Some(player) = game.get_player(name) {
+                    send_message(clients, client, &token_message(&session_token, player.name()));
+                }
         
-                let available_colors = game_guard.available_colors();
+                let available_colors = game.available_colors();
                 let color_options: String = available_colors
                     .iter()
                     .enumerate()
@@ -75,25 +95,30 @@ fn handle_message(message: &str, mut stream: &TcpStream, game: &Arc<Mutex<Game>>
                     .join("\n");
         
                 let color_options_message = format!("Choose your color by typing 'COLOR <color>' where <color> is one of the following:\n{}\nEND_OF_MESSAGE\n", color_options);
-                stream.write_all(color_options_message.as_bytes()).expect("Failed to send color options");
-                stream.flush().expect("Failed to flush stream");
+                send_message(clients, client, &color_options_message);
+
+                let host_message = match game.host() {
+                    Some(host) if *host == player_id(name) => "You are the host. Start the game with 'BEGIN' once everyone is ready.".to_string(),
+                    Some(host) => format!("{} is the host and will start the game once everyone is ready.", host),
+                    None => String::new(),
+                };
+                send_message(clients, client, &format!("{}\nEND_OF_MESSAGE\n", host_message));
             } else {
                 let response = "Please provide a name. Usage: JOIN <name>\nEND_OF_MESSAGE\n";
-                stream.write_all(response.as_bytes()).expect("Failed to write response");
+                send_message(clients, client, response);
             }
         },
         
         "COLOR" => {
-            if let Some(player_name) = get_player_name_from_connection(&stream, &game) {
+            if let Some(player_name) = seat_of(client, game) {
                 if let Some(color_str) = command_parts.get(1) {
-                    let mut game_guard = game.lock().unwrap();
         
-                    let num_players = game_guard.num_players();
+                    let num_players = game.num_players();
         
                     match parse_color(color_str) {
-                        Some(color) if game_guard.available_colors().contains(&color) => {
-                            if let Some(player) = game_guard.get_player_mut(&player_name.to_lowercase()) {
-                                player.set_color(color.clone());
+                        Some(color) if game.available_colors().contains(&color) => {
+                            game.set_player_color(&player_name, color.clone());
+                            if let Some(player) = game.get_player_mut(&player_name) {
                                 player.mark_as_fully_registered();
         
                                 let success_message = format!(
@@ -101,51 +126,82 @@ fn handle_message(message: &str, mut stream: &TcpStream, game: &Arc<Mutex<Game>>
                                     player_name, color
                                 );
         
-                                broadcast_message(&success_message, Some(&stream), &clients);
+                                broadcast_message(&success_message, Some(client), clients);
         
-                                let personal_success_message = format!("You have successfully registered with color {:?}. Waiting for other players...\nEND_OF_MESSAGE\n", color);
-                                stream.write_all(personal_success_message.as_bytes()).expect("Failed to send personal confirmation");
+                                let personal_success_message = format!("You have successfully registered with color {:?}. Type 'READY' when you are ready to play.\nEND_OF_MESSAGE\n", color);
+                                send_message(clients, client, &personal_success_message);
         
-                                if num_players >= 2 {
-                                    let start_game_message = format!(
-                                        "{} players have joined. Anyone can start the game by typing 'BEGIN'.\nEND_OF_MESSAGE\n",
-                                        num_players
-                                    );
-                                    broadcast_message(&start_game_message, None, &clients);
+                                if game.has_enough_players() {
+                                    let players_message = format!("{} players have joined.\nEND_OF_MESSAGE\n", num_players);
+                                    broadcast_message(&players_message, None, clients);
                                 }
                             } else {
                                 let error_message = "Error: Player not found or already registered.\nEND_OF_MESSAGE\n";
-                                stream.write_all(error_message.as_bytes()).expect("Failed to write response");
-                                stream.flush().expect("Failed to flush stream");
+                                send_message(clients, client, error_message);
                             }
                         },
                         _ => {
                             let response = "Invalid color choice or color not available.\nEND_OF_MESSAGE\n";
-                            stream.write_all(response.as_bytes()).expect("Failed to write response");
-                            stream.flush().expect("Failed to flush stream");
+                            send_message(clients, client, response);
                         }
                     }
                 }
             } else {
                 let error_message = "Unable to identify player.\nEND_OF_MESSAGE\n";
-                stream.write_all(error_message.as_bytes()).expect("Failed to write response");
-                stream.flush().expect("Failed to flush stream");
+                send_message(clients, client, error_message);
             }
         },
         
+        "READY" => {
+            let Some(player_name) = seat_of(client, game) else {
+                send_message(clients, client, "Unable to identify player.\nEND_OF_MESSAGE\n");
+                return Ok(());
+            };
+            if game.is_in_progress() || game.is_over() {
+                send_message(clients, client, "Game has already started.\nEND_OF_MESSAGE\n");
+                return Ok(());
+            }
+
+            let ready = !game.get_player(&player_name).is_some_and(|player| player.is_ready());
+            if !game.set_ready(&player_name, ready) {
+                send_message(clients, client, "Choose a color before getting ready.\nEND_OF_MESSAGE\n");
+                return Ok(());
+            }
+            let ready_message = if ready {
+                format!("{} is ready.\nEND_OF_MESSAGE\n", player_name)
+            } else {
+                format!("{} is no longer ready.\nEND_OF_MESSAGE\n", player_name)
+            };
+            broadcast_message(&ready_message, None, clients);
+
+            if let (Ok(()), Some(host)) = (game.check_start(), game.host()) {
+                let everyone_ready_message = format!("Everyone is ready. {} can start the game with 'BEGIN'.\nEND_OF_MESSAGE\n", host);
+                broadcast_message(&everyone_ready_message, None, clients);
+            }
+        },
+
         "BEGIN" => {
-            let mut game_guard = game.lock().unwrap();
-            if game_guard.num_players() >= 2 && !game_guard.is_in_progress() {
-                game_guard.start_game();
+            let player_name = seat_of(client, game);
+            let start = game.check_start();
+            if game.is_over() {
+                let response = "The game is over. Type 'REMATCH' to play again.\nEND_OF_MESSAGE\n";
+                send_message(clients, client, response);
+            } else if let (Some(host), false) = (game.host(), player_name.as_ref() == game.host()) {
+                let response = format!("Only the host ({}) can start the game.\nEND_OF_MESSAGE\n", host);
+                send_message(clients, client, &response);
+            } else if let Err(e) = start {
+                send_message(clients, client, &format!("{}\nEND_OF_MESSAGE\n", e));
+            } else {
+                game.start_game();
         
-                for player in game_guard.get_players_mut().values_mut() {
+                for player in game.get_players_mut().values_mut() {
                     player.initialize_pawns();
                 }
         
-                let turn_order_message = game_guard.get_turn_order_message();
-                let pawn_positions_message = game_guard.get_pawn_positions_message();
+                let turn_order_message = game.get_turn_order_message();
+                let pawn_positions_message = game.get_pawn_positions_message();
         
-                let current_turn = game_guard.get_current_turn()
+                let current_turn = game.get_current_turn()
                     .cloned()
                     .unwrap_or_else(|| "Unknown".to_string());
         
@@ -153,290 +209,184 @@ fn handle_message(message: &str, mut stream: &TcpStream, game: &Arc<Mutex<Game>>
                     "Game started!\n{}\n{}\nGood luck to all players!\nIt's {}'s turn to roll the dice.\nEND_OF_MESSAGE\n",
                     turn_order_message, pawn_positions_message, current_turn
                 );
-                broadcast_message(&game_start_message, None, &clients);
+                broadcast_message(&game_start_message, None, clients);
         
-                game_guard.set_status(GameStatus::InProgress);
-            } else if game_guard.is_in_progress() {
-                let response = "Game has already started.\nEND_OF_MESSAGE\n";
-                stream.write_all(response.as_bytes()).expect("Failed to write response");
-            } else {
-                let response = "Not enough players to start the game.\nEND_OF_MESSAGE\n";
-                stream.write_all(response.as_bytes()).expect("Failed to write response");
+                game.set_status(GameStatus::InProgress);
+
+                if let Some(commitment) = game.dice_commitment() {
+                    broadcast_message(&format!("DICE COMMIT {}\nEND_OF_MESSAGE\n", commitment), None, clients);
+                    let fair_dice_message = format!(
+                        "The dice are fair: the server has committed to a secret with SHA-256 {} and will reveal it when the game is over.\nEvery player adds entropy with 'ENTROPY <text>' before the first roll.\nEND_OF_MESSAGE\n",
+                        commitment
+                    );
+                    broadcast_message(&fair_dice_message, None, clients);
+                }
             }
         },
         
-        "ROLL" => {
-            println!("Received ROLL command");
-        
-            let player_addr_str = match stream.peer_addr() {
-                Ok(addr) => {
-                    println!("Player's Address for ROLL: {:?}", addr);
-                    addr.to_string()
-                },
-                Err(e) => {
-                    eprintln!("Error getting player's address: {}", e);
-                    return;
-                }
+        "ENTROPY" => {
+            let Some(player_name) = seat_of(client, game) else {
+                send_message(clients, client, "Unable to identify player.\nEND_OF_MESSAGE\n");
+                return Ok(());
             };
-        
-            let mut game_guard = game.lock().unwrap();
-        
-            if let Some(current_turn) = game_guard.get_current_turn().cloned() {
-                let (player_socket_addr, (pawns_in_house, pawns_on_board)) = {
-                    let current_player = game_guard.get_player_mut(&current_turn).expect("Current player not found");
-                    (current_player.socket_addr.clone(), current_player.get_pawn_counts())
-                };
-        
-                if player_socket_addr.to_string() == player_addr_str {
-                    println!("It's {}'s turn", current_turn);
-        
-                    let dice_value = rand::thread_rng().gen_range(1..=6);
-                    game_guard.set_last_dice_roll(&current_turn, dice_value);
-        
-                    let broadcast_roll_message = format!("{} rolled a {}.\nEND_OF_MESSAGE\n", current_turn, dice_value);
-                    broadcast_message(&broadcast_roll_message, Some(&stream), &clients);
-
-                    let personal_roll_message = format!("You rolled a {}.\nEND_OF_MESSAGE\n", dice_value);
-                    stream.write_all(personal_roll_message.as_bytes()).expect("Failed to send roll message");
-
-                    match (pawns_in_house, pawns_on_board, dice_value) {
-                        (4, 0, 6) => {
-                            let move_out_message = "You can move a pawn out of the house. Type 'MOVE_OUT'.\nEND_OF_MESSAGE\n";
-                            stream.write_all(move_out_message.as_bytes()).expect("Failed to send move out message");
-                        },
-                        (4, 0, _) => {
-                            let cannot_move_message = "You need a 6 to move a pawn out of the house.\nEND_OF_MESSAGE\n";
-                            stream.write_all(cannot_move_message.as_bytes()).expect("Failed to send cannot move message");
-
-                            let board_state = game_guard.get_board_state();
-                            broadcast_message(&format!("{}\nEND_OF_MESSAGE\n", board_state), None, &clients);
-                    
-                            game_guard.next_turn();
-                            notify_next_player_turn(&game_guard, &clients);
-                        },
-                            (1..=3, _, 6) => {
-                            let move_message = "Type 'MOVE_OUT' to move a pawn out of the house or 'MOVE <pawn number>' to move a pawn on the board.\nEND_OF_MESSAGE\n";
-                            stream.write_all(move_message.as_bytes()).expect("Failed to send move message");
-                        },
+            let Some(value) = command_parts.get(1) else {
+                send_message(clients, client, "Please provide some entropy. Usage: ENTROPY <text>\nEND_OF_MESSAGE\n");
+                return Ok(());
+            };
+            if let Err(e) = game.add_entropy(&player_name, value) {
+                send_message(clients, client, &format!("{}\nEND_OF_MESSAGE\n", e));
+                return Ok(());
+            }
 
+            broadcast_message(&format!("DICE ENTROPY {} {}\nEND_OF_MESSAGE\n", player_name, value), None, clients);
+            broadcast_message(&format!("{} added entropy to the dice.\nEND_OF_MESSAGE\n", player_name), None, clients);
+            if let (true, Some(current_turn)) = (game.awaiting_entropy().is_empty(), game.get_current_turn()) {
+                let dice_ready_message = format!("Every player has added entropy. {} can roll the dice.\nEND_OF_MESSAGE\n", current_turn);
+                broadcast_message(&dice_ready_message, None, clients);
+            }
+        }
 
-                        (_, _, _) => {
-                            if let Some(current_player) = game_guard.get_player_mut(&current_turn) {
-                                let (pawns_in_house, pawns_on_board) = current_player.get_pawn_counts();
-                                if pawns_in_house == 3 && pawns_on_board == 1 {
-                                    if let Some(pawn_number) = current_player.first_pawn_on_board_number() {
-                                        current_player.move_pawn(pawn_number, dice_value);
-                
-                                        if current_player.is_pawn_in_goal(pawn_number) {
-                                            let goal_message = format!("Your pawn {} reached the goal!\nEND_OF_MESSAGE\n", pawn_number);
-                                            stream.write_all(goal_message.as_bytes()).expect("Failed to send goal message");
-                                        } else {
-                                            let auto_move_message = format!("Your pawn {} on the board has been moved.\nEND_OF_MESSAGE\n", pawn_number);
-                                            stream.write_all(auto_move_message.as_bytes()).expect("Failed to send auto move message");
-                                        }
-
-                                        if current_player.all_pawns_in_goal() {
-                                            let winner_announcement = format!("{} has won the game!\nType 'END' to close the game.\nEND_OF_MESSAGE\n", current_turn);
-                                            broadcast_message(&winner_announcement, None, &clients);
-                                            game_guard.set_status(GameStatus::GameOver);
-                                            return;
-                                        }
-                                    }
-                
-                                    let board_state = game_guard.get_board_state();
-                                    broadcast_message(&format!("{}\nEND_OF_MESSAGE\n", board_state), None, &clients);
-                            
-                                    game_guard.next_turn();
-                                    notify_next_player_turn(&game_guard, &clients);
-                                } else {
-                                    let move_prompt = "Choose a pawn to move. Type 'MOVE <pawn number>'.\nEND_OF_MESSAGE\n";
-                                    stream.write_all(move_prompt.as_bytes()).expect("Failed to send move prompt");
-                                }
-                            } else {
-                                let error_message = "Error: Current player not found.\nEND_OF_MESSAGE\n";
-                                stream.write_all(error_message.as_bytes()).expect("Failed to send error message");
-                            }
-                        }
-                    }
+        "ROLL" => {
+            let Some(player_name) = seat_of(client, game) else {
+                send_message(clients, client, "Unable to identify player.\nEND_OF_MESSAGE\n");
+                return Ok(());
+            };
 
-                } else {
-                    let not_your_turn_message = format!("It's not your turn, it's {}'s turn.\nEND_OF_MESSAGE\n", current_turn);
-                    stream.write_all(not_your_turn_message.as_bytes()).expect("Failed to write response");
+            let (dice_value, outcome) = match game.roll(&player_name) {
+                Ok(rolled) => rolled,
+                Err(e) => {
+                    send_message(clients, client, &format!("{}\nEND_OF_MESSAGE\n", e));
+                    return Ok(());
                 }
-            } else {
-                let error_message = "The game hasn't started yet.\nEND_OF_MESSAGE\n";
-                stream.write_all(error_message.as_bytes()).expect("Failed to write response");
+            };
+
+            if game.dice_commitment().is_some() {
+                broadcast_message(&format!("DICE ROLL {} {}\nEND_OF_MESSAGE\n", player_name, dice_value), None, clients);
+            }
+            let broadcast_roll_message = format!("{} rolled a {}.\nEND_OF_MESSAGE\n", player_name, dice_value);
+            broadcast_message(&broadcast_roll_message, Some(client), clients);
+            send_message(clients, client, &format!("You rolled a {}.\nEND_OF_MESSAGE\n", dice_value));
+
+            match outcome {
+                RollOutcome::MustMoveOut => {
+                    let move_out_message = "You can move a pawn out of the house. Type 'MOVE_OUT'.\nEND_OF_MESSAGE\n";
+                    send_message(clients, client, move_out_message);
+                },
+                RollOutcome::MoveOrMoveOut => {
+                    let move_message = "Type 'MOVE_OUT' to move a pawn out of the house or 'MOVE <pawn number>' to move a pawn on the board.\nEND_OF_MESSAGE\n";
+                    send_message(clients, client, move_message);
+                },
+                RollOutcome::ChoosePawn => {
+                    let move_prompt = "Choose a pawn to move. Type 'MOVE <pawn number>'.\nEND_OF_MESSAGE\n";
+                    send_message(clients, client, move_prompt);
+                },
+                RollOutcome::NoMove => {
+                    let cannot_move_message = format!("You need a {} to move a pawn out of the house.\nEND_OF_MESSAGE\n", game.rules().exit_roll);
+                    send_message(clients, client, &cannot_move_message);
+                    announce_move(game, clients);
+                },
+                RollOutcome::AutoMoved(moved) => {
+                    let auto_move_message = if moved.reached_goal {
+                        format!("Your pawn {} reached the goal!\nEND_OF_MESSAGE\n", moved.pawn)
+                    } else {
+                        format!("Your pawn {} on the board has been moved.\nEND_OF_MESSAGE\n", moved.pawn)
+                    };
+                    send_message(clients, client, &auto_move_message);
+                    announce_move_result(&player_name, &moved, game, clients);
+                },
             }
         }
-        
 
         "MOVE_OUT" => {
-            let player_addr_str = match stream.peer_addr() {
-                Ok(addr) => addr.to_string(),
-                Err(e) => {
-                    eprintln!("Error getting player's address: {}", e);
-                    return;
-                }
+            let Some(player_name) = seat_of(client, game) else {
+                send_message(clients, client, "Unable to identify player.\nEND_OF_MESSAGE\n");
+                return Ok(());
             };
-        
-            let mut game_guard = game.lock().unwrap();
-            let current_turn = game_guard.get_current_turn().cloned();
-        
-            if let Some(current_turn) = current_turn {
-                let last_dice_roll = game_guard.get_last_dice_roll(&current_turn);
-        
-                if let Some(current_player) = game_guard.get_player_mut(&current_turn) {
-                    if current_player.socket_addr.to_string() == player_addr_str {
-                        if let Some(6) = last_dice_roll {
-                            let (pawns_in_house, _) = current_player.get_pawn_counts();
-        
-                            if pawns_in_house >= 1 {
-                                current_player.move_pawn_out();
-        
-                                let move_out_success_message = "A pawn has been moved out of the house.\nEND_OF_MESSAGE\n";
-                                stream.write_all(move_out_success_message.as_bytes()).expect("Failed to send move out success message");
-        
-                                let board_state = game_guard.get_board_state();
-                                broadcast_message(&format!("{}\nEND_OF_MESSAGE\n", board_state), None, &clients);
-        
-                                game_guard.next_turn();
-                                notify_next_player_turn(&game_guard, &clients);
-                            } else {
-                                let invalid_move_out_message = "You cannot move a pawn out right now.\nEND_OF_MESSAGE\n";
-                                stream.write_all(invalid_move_out_message.as_bytes()).expect("Failed to send invalid move out message");
-                            }
-                        } else {
-                            let not_your_turn_message = "It's not your turn.\nEND_OF_MESSAGE\n";
-                            stream.write_all(not_your_turn_message.as_bytes()).expect("Failed to write response");
-                        }
-                    } else {
-                        println!("Current turn player not found.");
-                    }
-                } else {
-                    let error_message = "The game hasn't started yet.\nEND_OF_MESSAGE\n";
-                    stream.write_all(error_message.as_bytes()).expect("Failed to write response");
+
+            match game.move_pawn_out(&player_name) {
+                Ok(()) => {
+                    let move_out_success_message = "A pawn has been moved out of the house.\nEND_OF_MESSAGE\n";
+                    send_message(clients, client, move_out_success_message);
+                    announce_move(game, clients);
                 }
+                Err(e) => send_message(clients, client, &format!("{}\nEND_OF_MESSAGE\n", e)),
             }
         }
 
         "MOVE" => {
-            let player_addr_str = match stream.peer_addr() {
-                Ok(addr) => addr.to_string(),
-                Err(e) => {
-                    eprintln!("Error getting player's address: {}", e);
-                    return;
-                }
+            let Some(player_name) = seat_of(client, game) else {
+                send_message(clients, client, "Unable to identify player.\nEND_OF_MESSAGE\n");
+                return Ok(());
             };
-        
-            let current_turn_clone;
-            let last_dice_roll;
-        
-            {
-                let game_guard = game.lock().unwrap();
-                current_turn_clone = game_guard.get_current_turn().cloned();
-                last_dice_roll = game_guard.get_last_dice_roll(&current_turn_clone.clone().unwrap_or_default());
-            }
-        
-            let move_result = {
-                let mut game_guard = game.lock().unwrap();
-        
-                if let Some(current_turn) = &current_turn_clone {
-                    if let Some(current_player) = game_guard.get_player_mut(current_turn) {
-                        if current_player.socket_addr.to_string() == player_addr_str {
-                            if let Some(pawn_number_str) = command_parts.get(1) {
-                                if let Ok(pawn_number) = pawn_number_str.parse::<usize>() {
-                                    if current_player.is_valid_pawn_number(pawn_number) && !current_player.is_pawn_in_goal(pawn_number) {
-                                        if let Some(dice_value) = last_dice_roll {
-                                            current_player.move_pawn(pawn_number, dice_value);
-                                            Some((current_turn.clone(), current_player.all_pawns_in_goal()))
-                                        } else {
-                                            let error_message = "No dice roll found.\nEND_OF_MESSAGE\n";
-                                            stream.write_all(error_message.as_bytes()).expect("Failed to send error message");
-                                            None
-                                        }
-                                    } else {
-                                        let error_message = "Invalid pawn number, pawn not on board, or pawn already in goal.\nEND_OF_MESSAGE\n";
-                                        stream.write_all(error_message.as_bytes()).expect("Failed to send error message");
-                                        None
-                                    }
-                                } else {
-                                    let error_message = "Invalid pawn number format.\nEND_OF_MESSAGE\n";
-                                    stream.write_all(error_message.as_bytes()).expect("Failed to send error message");
-                                    None
-                                }
-                            } else {
-                                let error_message = "Please specify which pawn to move (e.g., 'MOVE 1').\nEND_OF_MESSAGE\n";
-                                stream.write_all(error_message.as_bytes()).expect("Failed to send error message");
-                                None
-                            }
-                        } else {
-                            let not_your_turn_message = "It's not your turn.\nEND_OF_MESSAGE\n";
-                            stream.write_all(not_your_turn_message.as_bytes()).expect("Failed to write response");
-                            None
-                        }
-                    } else {
-                        let error_message = "Current turn player not found.\nEND_OF_MESSAGE\n";
-                        stream.write_all(error_message.as_bytes()).expect("Failed to send error message");
-                        None
-                    }
-                } else {
-                    let error_message = "The game hasn't started yet.\nEND_OF_MESSAGE\n";
-                    stream.write_all(error_message.as_bytes()).expect("Failed to write response");
-                    None
-                }
+            let Some(pawn_number_str) = command_parts.get(1) else {
+                let error_message = "Please specify which pawn to move (e.g., 'MOVE 1').\nEND_OF_MESSAGE\n";
+                send_message(clients, client, error_message);
+                return Ok(());
             };
-        
-            if let Some((current_turn, all_pawns_in_goal)) = move_result {
-                let mut game_guard = game.lock().unwrap();
-                let board_state = game_guard.get_board_state();
-                broadcast_message(&format!("{}\nEND_OF_MESSAGE\n", board_state), None, &clients);
-        
-                game_guard.next_turn();
-                notify_next_player_turn(&game_guard, &clients);
-        
-                if all_pawns_in_goal {
-                    let winner_announcement = format!("{} has won the game!\nType 'END' to close the game.\nEND_OF_MESSAGE\n", current_turn);
-                    broadcast_message(&winner_announcement, None, &clients);
-                    game_guard.set_status(GameStatus::GameOver);
-                }
+            let Ok(pawn_number) = pawn_number_str.parse::<usize>() else {
+                send_message(clients, client, "Invalid pawn number format.\nEND_OF_MESSAGE\n");
+                return Ok(());
+            };
+
+            match game.move_pawn(&player_name, pawn_number) {
+                Ok(moved) => announce_move_result(&player_name, &moved, game, clients),
+                Err(e) => send_message(clients, client, &format!("{}\nEND_OF_MESSAGE\n", e)),
             }
         },
         
         _ => {
             println!("Unknown command received: {}", message);
             let error_message = "Unknown or invalid command.\nEND_OF_MESSAGE\n";
-            stream.write_all(error_message.as_bytes()).expect("Failed to send error message");
-
+            send_message(clients, client, error_message);
         }
     }
+
+    Ok(())
 }
 
 
 //METHODS
 
-fn notify_next_player_turn(game_guard: &MutexGuard<Game>, clients: &Arc<Mutex<Vec<TcpStream>>>) {
-    if let Some(next_player) = game_guard.get_current_turn() {
-        let next_turn_message_personal = "It's now your turn to roll the dice.\nEND_OF_MESSAGE\n";
-        let next_turn_message_broadcast = format!("It's now {}'s turn to roll the dice.\nEND_OF_MESSAGE\n", next_player);
+/// Shows everyone the board after a move and hands the turn to the next player.
+fn announce_move(game: &Game, clients: &mut Clients) {
+    let board_state = game.get_board_state();
+    broadcast_message(&format!("{}\nEND_OF_MESSAGE\n", board_state), None, clients);
+    notify_next_player_turn(game, clients);
+}
 
-        for mut client in clients.lock().unwrap().iter() {
-            let client_addr_str = client.peer_addr().unwrap().to_string();
-            if let Some(next_player_data) = game_guard.get_player(next_player) {
-                if next_player_data.socket_addr.to_string() == client_addr_str {
-                    client.write_all(next_turn_message_personal.as_bytes()).expect("Failed to send personal turn message");
-                } else {
-                    client.write_all(next_turn_message_broadcast.as_bytes()).expect("Failed to send broadcast turn message");
-                }
-            }
+fn announce_move_result(player_name: &str, moved: &MoveOutcome, game: &Game, clients: &mut Clients) {
+    if moved.game_over {
+        let board_state = game.get_board_state();
+        broadcast_message(&format!("{}\nEND_OF_MESSAGE\n", board_state), None, clients);
+        let winner = game.standings().into_iter().next().unwrap_or_else(|| player_name.to_string());
+        let winner_announcement = format!("{} has won the game!\n{}Type 'REMATCH' to play again, 'LEAVE' to leave the table or 'END' to close the room.\nEND_OF_MESSAGE\n", winner, game.get_standings_message());
+        broadcast_message(&winner_announcement, None, clients);
+        if let Some(secret) = game.revealed_secret() {
+            broadcast_message(&format!("DICE REVEAL {}\nEND_OF_MESSAGE\n", secret), None, clients);
+            let reveal_message = format!("The fair dice secret was {}. Its SHA-256 is the commitment announced at the start, and every roll can be worked out from it.\nEND_OF_MESSAGE\n", secret);
+            broadcast_message(&reveal_message, None, clients);
         }
+        return;
     }
-}
 
+    if let Some(place) = moved.place {
+        let finished_message = format!("{} brought every pawn home and finished in place {}!\nEND_OF_MESSAGE\n", player_name, place);
+        broadcast_message(&finished_message, None, clients);
+    }
+    announce_move(game, clients);
+}
 
+fn notify_next_player_turn(game: &Game, clients: &mut Clients) {
+    if let Some(next_player) = game.get_current_turn() {
+        let Some(next_player_data) = game.get_player(next_player) else {
+            return;
+        };
+        let next_turn_message_personal = "It's now your turn to roll the dice.\nEND_OF_MESSAGE\n";
+        let next_turn_message_broadcast = format!("It's now {}'s turn to roll the dice.\nEND_OF_MESSAGE\n", next_player);
 
-fn remove_client(stream: &TcpStream, clients: &Arc<Mutex<Vec<TcpStream>>>) {
-    let mut clients = clients.lock().unwrap();
-    clients.retain(|client| client.peer_addr().unwrap() != stream.peer_addr().unwrap());
+        broadcast_message(&next_turn_message_broadcast, Some(next_player_data.client_id), clients);
+        send_message(clients, next_player_data.client_id, next_turn_message_personal);
+    }
 }
 
 fn parse_color(color_str: &str) -> Option<Color> {
@@ -449,24 +399,12 @@ fn parse_color(color_str: &str) -> Option<Color> {
     }
 }
 
-fn get_player_name_from_connection(stream: &TcpStream, game: &Arc<Mutex<Game>>) -> Option<String> {
-    let player_addr = match stream.peer_addr() {
-        Ok(addr) => {
-            println!("Player's Address: {:?}", addr);
-            addr
-        },
-        Err(e) => {
-            eprintln!("Error getting player's address: {}", e);
-            return None;
-        }
-    };
-
-    let game_guard = game.lock().unwrap();
+/// `TOKEN <session token> <name>`: what to `RESUME` the seat with, and whose seat it is.
+pub fn token_message(token: &str, name: &str) -> String {
+    format!("TOKEN {} {}\nEND_OF_MESSAGE\n", token, name)
+}
 
-    for (name, player) in game_guard.iter_players() {
-        if player.socket_addr == player_addr {
-            return Some(name.clone());
-        }
-    }
-    None
-}
\ No newline at end of file
+/// The canonical id of the seat `client` holds.
+fn seat_of(client: ClientId, game: &Game) -> Option<String> {
+    game.get_player_by_client(client).map(|(id, _)| id.clone())
+}
@@ -1,19 +1,192 @@
-use std::net::TcpStream;
-use std::sync::Mutex;
-use std::io::Write;
+use std::collections::HashMap;
+use std::io;
+use tokio::io::{AsyncBufReadExt, AsyncRead, BufReader};
+use tokio::sync::mpsc::{self, error::TrySendError};
 
-pub fn broadcast_message(message: &str, sender: Option<&TcpStream>, clients: &Mutex<Vec<TcpStream>>) {
-    let mut clients_guard = clients.lock().unwrap();
+pub const MAX_LINE_LENGTH: usize = 512;
 
-    for client in clients_guard.iter_mut() {
-        if let Some(sender_stream) = sender {
-            if client.peer_addr().unwrap() == sender_stream.peer_addr().unwrap() {
+/// Number of messages that may wait for a slow client before it is disconnected.
+pub const OUTBOUND_QUEUE_SIZE: usize = 256;
+
+pub use parchis_core::game_state::ClientId;
+
+pub enum Frame {
+    Line(String),
+    TooLong,
+}
+
+/// Splits the incoming byte stream into newline-terminated commands.
+/// Blank lines are skipped and lines longer than `MAX_LINE_LENGTH` are discarded.
+pub struct FrameReader<R: AsyncRead + Unpin> {
+    reader: BufReader<R>,
+    pending: Vec<u8>,
+    discarding: bool,
+}
+
+impl<R: AsyncRead + Unpin> FrameReader<R> {
+    pub fn new(inner: R) -> Self {
+        FrameReader {
+            reader: BufReader::new(inner),
+            pending: Vec::new(),
+            discarding: false,
+        }
+    }
+
+    /// Returns the next frame, or `None` once the peer has closed the connection.
+    pub async fn next_frame(&mut self) -> io::Result<Option<Frame>> {
+        loop {
+            let available = self.reader.fill_buf().await?;
+            if available.is_empty() {
+                return Ok(None);
+            }
+
+            let (chunk, found_newline) = match available.iter().position(|&b| b == b'\n') {
+                Some(index) => (&available[..index], true),
+                None => (available, false),
+            };
+
+            if !self.discarding {
+                self.pending.extend_from_slice(chunk);
+            }
+            let consumed = chunk.len() + usize::from(found_newline);
+            self.reader.consume(consumed);
+
+            if !self.discarding && self.pending.len() > MAX_LINE_LENGTH {
+                self.pending.clear();
+                self.discarding = true;
+            }
+
+            if !found_newline {
                 continue;
             }
+
+            if self.discarding {
+                self.discarding = false;
+                return Ok(Some(Frame::TooLong));
+            }
+
+            let line = String::from_utf8_lossy(&self.pending).trim().to_string();
+            self.pending.clear();
+            if !line.is_empty() {
+                return Ok(Some(Frame::Line(line)));
+            }
+        }
+    }
+}
+
+/// Outbound queues of every connected client, keyed by connection.
+/// Dropping a client's sender closes its writer task and with it the connection.
+#[derive(Default)]
+pub struct Clients {
+    outbound: HashMap<ClientId, mpsc::Sender<String>>,
+}
+
+impl Clients {
+    pub fn new() -> Self {
+        Clients::default()
+    }
+
+    pub fn add(&mut self, client: ClientId, sender: mpsc::Sender<String>) {
+        self.outbound.insert(client, sender);
+    }
+
+    pub fn remove(&mut self, client: ClientId) {
+        self.outbound.remove(&client);
+    }
+
+    /// Removes a client and hands back its outbound queue, e.g. to move it to another room.
+    pub fn take(&mut self, client: ClientId) -> Option<mpsc::Sender<String>> {
+        self.outbound.remove(&client)
+    }
+
+    pub fn contains(&self, client: ClientId) -> bool {
+        self.outbound.contains_key(&client)
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.outbound.is_empty()
+    }
+
+    pub fn ids(&self) -> Vec<ClientId> {
+        self.outbound.keys().copied().collect()
+    }
+}
+
+/// Queues `message` for a single client without waiting.
+/// A client whose queue is full or closed is disconnected rather than allowed to stall the game.
+pub fn send_message(clients: &mut Clients, client: ClientId, message: &str) {
+    let Some(sender) = clients.outbound.get(&client) else {
+        return;
+    };
+
+    match sender.try_send(message.to_string()) {
+        Ok(()) => {}
+        Err(TrySendError::Full(_)) => {
+            eprintln!("Dropping client {}: outbound queue is full", client);
+            clients.remove(client);
         }
+        Err(TrySendError::Closed(_)) => {
+            clients.remove(client);
+        }
+    }
+}
 
-        client.write_all(message.as_bytes()).unwrap();
-        client.flush().unwrap();
+/// Sends `message` to every connected client except `sender`.
+pub fn broadcast_message(message: &str, sender: Option<ClientId>, clients: &mut Clients) {
+    for client in clients.ids() {
+        if Some(client) != sender {
+            send_message(clients, client, message);
+        }
     }
 }
 
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Every frame in `input`, as the lines themselves or `None` for a line that was too long.
+    async fn frames(input: &[u8]) -> Vec<Option<String>> {
+        let mut reader = FrameReader::new(input);
+        let mut frames = Vec::new();
+        while let Some(frame) = reader.next_frame().await.unwrap() {
+            frames.push(match frame {
+                Frame::Line(line) => Some(line),
+                Frame::TooLong => None,
+            });
+        }
+        frames
+    }
+
+    #[tokio::test]
+    async fn splits_lines_and_skips_blank_ones() {
+        assert_eq!(frames(b"JOIN ana\r\n\n   \nROLL\n").await, vec![Some("JOIN ana".to_string()), Some("ROLL".to_string())]);
+    }
+
+    #[tokio::test]
+    async fn drops_an_unterminated_last_line() {
+        assert_eq!(frames(b"ROLL\nMOVE").await, vec![Some("ROLL".to_string())]);
+    }
+
+    #[tokio::test]
+    async fn discards_long_lines_and_carries_on() {
+        let mut input = vec![b'x'; MAX_LINE_LENGTH + 1];
+        input.extend_from_slice(b"\nROLL\n");
+        assert_eq!(frames(&input).await, vec![None, Some("ROLL".to_string())]);
+    }
+
+    #[tokio::test]
+    async fn accepts_a_line_of_the_maximum_length() {
+        let line = "x".repeat(MAX_LINE_LENGTH);
+        assert_eq!(frames(format!("{}\n", line).as_bytes()).await, vec![Some(line)]);
+    }
+
+    #[tokio::test]
+    async fn discards_long_lines_split_across_reads() {
+        // A buffer smaller than the line makes the reader see it in pieces.
+        let mut input = vec![b'x'; 3 * MAX_LINE_LENGTH];
+        input.extend_from_slice(b"\nROLL\n");
+        let mut reader = FrameReader { reader: BufReader::with_capacity(64, &input[..]), pending: Vec::new(), discarding: false };
+        assert!(matches!(reader.next_frame().await.unwrap(), Some(Frame::TooLong)));
+        assert!(matches!(reader.next_frame().await.unwrap(), Some(Frame::Line(line)) if line == "ROLL"));
+    }
+}
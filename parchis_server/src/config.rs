@@ -0,0 +1,99 @@
+use std::path::PathBuf;
+use parchis_core::dice::ScriptedDice;
+use crate::error::{ServerError, ServerResult};
+
+pub const USAGE: &str = "Usage: parchis_server [--bind <address>] [--port <port>] [--max-rooms <n>] [--rules <file>] [--replay-dir <dir>]\n       [--save-dir <dir>] [--load <file>] [--data-dir <dir>]\n       [--seed <n>] [--dice <roll,roll,...>] [--fair-dice]";
+
+pub struct Config {
+    pub bind: String,
+    pub port: u16,
+    pub max_rooms: usize,
+    pub rules_file: Option<PathBuf>,
+    /// Where a replay file is written for every finished game.
+    pub replay_dir: PathBuf,
+    /// Where `SAVE` writes games.
+    pub save_dir: PathBuf,
+    /// Saved game to put back on a table at startup.
+    pub load_file: Option<PathBuf>,
+    /// Where running games are journaled so they survive a restart.
+    pub data_dir: PathBuf,
+    /// Seed for the dice of the first game; every later game takes the next one.
+    pub seed: Option<u64>,
+    /// Fixed rolls played over and over in every game, for testing.
+    pub dice_script: Option<String>,
+    /// Commit–reveal dice the players can check after the game.
+    pub fair_dice: bool,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Config {
+            bind: "127.0.0.1".to_string(),
+            port: 7878,
+            max_rooms: 4,
+            rules_file: None,
+            replay_dir: PathBuf::from("replays"),
+            save_dir: PathBuf::from("saves"),
+            load_file: None,
+            data_dir: PathBuf::from("data"),
+            seed: None,
+            dice_script: None,
+            fair_dice: false,
+        }
+    }
+}
+
+impl Config {
+    /// Parses the command-line arguments, not including the program name.
+    pub fn from_args(args: impl IntoIterator<Item = String>) -> ServerResult<Config> {
+        let mut config = Config::default();
+        let mut args = args.into_iter();
+
+        while let Some(flag) = args.next() {
+            if flag == "--help" || flag == "-h" {
+                println!("{}", USAGE);
+                std::process::exit(0);
+            }
+            if flag == "--fair-dice" {
+                config.fair_dice = true;
+                continue;
+            }
+
+            let value = args.next()
+                .ok_or_else(|| ServerError::InvalidConfig(format!("missing value for {}", flag)))?;
+            let invalid = || ServerError::InvalidConfig(format!("invalid value '{}' for {}", value, flag));
+
+            match flag.as_str() {
+                "--bind" => config.bind = value.clone(),
+                "--port" => config.port = value.parse().map_err(|_| invalid())?,
+                "--max-rooms" => {
+                    config.max_rooms = value.parse().map_err(|_| invalid())?;
+                    if config.max_rooms == 0 {
+                        return Err(invalid());
+                    }
+                }
+                "--rules" => config.rules_file = Some(PathBuf::from(&value)),
+                "--replay-dir" => config.replay_dir = PathBuf::from(&value),
+                "--save-dir" => config.save_dir = PathBuf::from(&value),
+                "--load" => config.load_file = Some(PathBuf::from(&value)),
+                "--data-dir" => config.data_dir = PathBuf::from(&value),
+                "--seed" => config.seed = Some(value.parse().map_err(|_| invalid())?),
+                "--dice" => {
+                    ScriptedDice::parse(&value).ok_or_else(invalid)?;
+                    config.dice_script = Some(value.clone());
+                }
+                _ => return Err(ServerError::InvalidConfig(format!("unknown option {}", flag))),
+            }
+        }
+
+        if config.fair_dice && (config.seed.is_some() || config.dice_script.is_some()) {
+            return Err(ServerError::InvalidConfig("--fair-dice cannot be combined with --seed or --dice".to_string()));
+        }
+
+        Ok(config)
+    }
+
+    pub fn address(&self) -> String {
+        format!("{}:{}", self.bind, self.port)
+    }
+}
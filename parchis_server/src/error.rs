@@ -0,0 +1,36 @@
+use std::fmt;
+use std::io;
+
+#[derive(Debug)]
+pub enum ServerError {
+    Io(io::Error),
+    GameUnavailable,
+    InvalidConfig(String),
+}
+
+pub type ServerResult<T> = Result<T, ServerError>;
+
+impl fmt::Display for ServerError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ServerError::Io(e) => write!(f, "I/O error: {}", e),
+            ServerError::GameUnavailable => write!(f, "the game task is no longer running"),
+            ServerError::InvalidConfig(reason) => write!(f, "invalid configuration: {}", reason),
+        }
+    }
+}
+
+impl std::error::Error for ServerError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            ServerError::Io(e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+impl From<io::Error> for ServerError {
+    fn from(e: io::Error) -> Self {
+        ServerError::Io(e)
+    }
+}
@@ -0,0 +1,1264 @@
+use std::collections::{HashMap, HashSet};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+use tokio::sync::mpsc;
+use crate::accounts::{Account, AccountError, AccountStore, GameRecord, SeatRecord};
+use crate::chat::ChatFilter;
+use crate::client_handler::{handle_message, token_message};
+use crate::communication::{broadcast_message, send_message, ClientId, Clients, MAX_LINE_LENGTH};
+use crate::config::Config;
+use crate::persistence::Journal;
+use crate::session::Session;
+use parchis_core::dice::{DiceHistogram, FairDice, ScriptedDice, SeededDice, CHI_SQUARED_CRITICAL};
+use parchis_core::events::{GameEvent, Replay};
+use parchis_core::game_state::{player_id, validate_name, Color, Game};
+use parchis_core::rules::Rules;
+
+pub const COMMAND_QUEUE_SIZE: usize = 256;
+
+/// How long a game in progress is kept after its last client left, waiting for a `RESUME`.
+const RESUME_GRACE: Duration = Duration::from_secs(5 * 60);
+
+/// How often the rooms' journals are written and timeouts checked.
+const TICK_INTERVAL: Duration = Duration::from_secs(1);
+
+/// How long fair dice wait for every player's entropy before rolling without the missing ones.
+const ENTROPY_TIMEOUT: Duration = Duration::from_secs(60);
+
+/// Failed `LOGIN`s to one account before it is locked for `LOGIN_LOCKOUT`.
+const MAX_FAILED_LOGINS: u32 = 5;
+const LOGIN_LOCKOUT: Duration = Duration::from_secs(5 * 60);
+
+/// Games listed by `HISTORY`.
+const HISTORY_LENGTH: usize = 10;
+/// Players listed by `LEADERBOARD`.
+const LEADERBOARD_LENGTH: usize = 10;
+
+pub enum ClientEvent {
+    Connected(mpsc::Sender<String>),
+    Line(String),
+    LineTooLong,
+    Disconnected,
+    /// The outcome of a `REGISTER` or `LOGIN` checked off the game loop.
+    AccountChecked(Result<Account, AccountError>),
+}
+
+pub struct GameCommand {
+    pub client: ClientId,
+    pub event: ClientEvent,
+}
+
+/// Hands out the dice for each new game, as set with `--seed`, `--dice` or
+/// `--fair-dice`. Without any, games keep their own randomly seeded dice.
+struct DiceDealer {
+    seed: Option<u64>,
+    script: Option<String>,
+    fair: bool,
+    games: u64,
+}
+
+impl DiceDealer {
+    fn deal(&mut self, game: &mut Game) {
+        if let Some(dice) = self.script.as_deref().and_then(ScriptedDice::parse) {
+            game.set_dice(Box::new(dice));
+        } else if self.fair {
+            game.set_dice(Box::new(FairDice::new()));
+        } else if let Some(seed) = self.seed {
+            game.set_dice(Box::new(SeededDice::new(seed.wrapping_add(self.games))));
+        }
+        self.games += 1;
+    }
+}
+
+/// A single table: its game and every client following it, seated or not.
+struct Room {
+    id: usize,
+    game: Game,
+    clients: Clients,
+    /// Clients in `clients` that only watch and never hold a seat.
+    spectators: HashSet<ClientId>,
+    /// Last `STATE` line sent, so clients are only updated when something changed.
+    last_state: String,
+    /// When the last client left a game in progress.
+    empty_since: Option<Instant>,
+    /// When the game got under way, for the game length in the stats.
+    started_at: Option<Instant>,
+    /// Whether the finished game has been written to a replay file.
+    replay_saved: bool,
+    /// Players of the finished game who asked for a `REMATCH`.
+    rematch_votes: HashSet<ClientId>,
+    journal: Journal,
+}
+
+/// Owns every room and every client's outbound queue. Connection tasks only talk
+/// to it through `commands`, so game state is never shared between tasks.
+struct Lobby {
+    rules: Rules,
+    max_rooms: usize,
+    next_room_id: usize,
+    rooms: Vec<Room>,
+    /// Connected clients that have not joined a room yet.
+    waiting: Clients,
+    chat_filter: ChatFilter,
+    replay_dir: PathBuf,
+    save_dir: PathBuf,
+    data_dir: PathBuf,
+    accounts: AccountStore,
+    /// Accounts clients have logged in to, by connection.
+    logins: HashMap<ClientId, Account>,
+    /// Usernames whose `REGISTER` or `LOGIN` is being checked, by connection.
+    pending_logins: HashMap<ClientId, String>,
+    /// Failed `LOGIN`s and the time of the last one, by lowercase username.
+    failed_logins: HashMap<String, (u32, Instant)>,
+    /// Where account checks send their outcome back to.
+    commands: mpsc::Sender<GameCommand>,
+    dice: DiceDealer,
+}
+
+/// Runs every room until the command queue closes. Games restored from the
+/// journal and a `loaded_game` get their rooms back first and wait there for
+/// their players to come back.
+pub async fn run_game(
+    mut commands: mpsc::Receiver<GameCommand>,
+    commands_tx: mpsc::Sender<GameCommand>,
+    rules: Rules,
+    config: Config,
+    accounts: AccountStore,
+    restored_games: Vec<(usize, Game, Journal)>,
+    loaded_game: Option<Game>,
+) {
+    let mut lobby = Lobby {
+        rules,
+        max_rooms: config.max_rooms,
+        next_room_id: 1,
+        rooms: Vec::new(),
+        waiting: Clients::new(),
+        chat_filter: ChatFilter::new(),
+        replay_dir: config.replay_dir,
+        save_dir: config.save_dir,
+        data_dir: config.data_dir,
+        accounts,
+        logins: HashMap::new(),
+        pending_logins: HashMap::new(),
+        failed_logins: HashMap::new(),
+        commands: commands_tx,
+        dice: DiceDealer { seed: config.seed, script: config.dice_script, fair: config.fair_dice, games: 0 },
+    };
+    for (room_id, game, journal) in restored_games {
+        if game.is_in_progress() {
+            println!("Restored room {}; waiting for {} to come back", room_id, game.unclaimed_seats().join(", "));
+            lobby.insert_room(room_id, game, journal);
+        } else {
+            journal.remove();
+        }
+    }
+    if let Some(game) = loaded_game {
+        lobby.add_room(game);
+    }
+
+    let mut ticks = tokio::time::interval(TICK_INTERVAL);
+    loop {
+        let GameCommand { client, event } = tokio::select! {
+            command = commands.recv() => match command {
+                Some(command) => command,
+                None => break,
+            },
+            _ = ticks.tick() => {
+                lobby.close_late_entropy();
+                lobby.flush_journals();
+                continue;
+            }
+        };
+        match event {
+            ClientEvent::Connected(outbound) => {
+                lobby.waiting.add(client, outbound);
+                let welcome_msg = "Welcome to Parchis! Enter 'JOIN <name>' to join the game or 'WATCH [room]' to watch one.\nEND_OF_MESSAGE\n";
+                send_message(&mut lobby.waiting, client, welcome_msg);
+            }
+            ClientEvent::Line(message) => {
+                println!("Received message from {}: {}", client, message);
+                lobby.handle_line(client, &message);
+            }
+            ClientEvent::LineTooLong => {
+                let error_message = format!("Command too long (max {} characters).\nEND_OF_MESSAGE\n", MAX_LINE_LENGTH);
+                send_message(lobby.clients_of(client), client, &error_message);
+            }
+            ClientEvent::Disconnected => {
+                lobby.disconnect(client);
+            }
+            ClientEvent::AccountChecked(result) => {
+                lobby.account_checked(client, result);
+            }
+        }
+
+        lobby.close_empty_rooms();
+    }
+}
+
+impl Lobby {
+    fn flush_journals(&mut self) {
+        // A finished game's journal is already gone and stays gone.
+        for room in self.rooms.iter_mut().filter(|room| !room.game.is_over()) {
+            if let Err(e) = room.journal.record(&room.game) {
+                eprintln!("Could not journal room {}: {}", room.id, e);
+            }
+        }
+    }
+
+    /// Lets fair dice roll in rooms that have waited `ENTROPY_TIMEOUT` for entropy.
+    fn close_late_entropy(&mut self) {
+        for room in &mut self.rooms {
+            let late = room.started_at.is_some_and(|started_at| started_at.elapsed() >= ENTROPY_TIMEOUT);
+            if !late || room.game.awaiting_entropy().is_empty() {
+                continue;
+            }
+            let missing = room.game.close_entropy();
+            println!("Room {} stopped waiting for entropy from {}", room.id, missing.join(", "));
+            let notice = format!(
+                "No entropy from {} after {}; the dice go on without it.{}\nEND_OF_MESSAGE\n",
+                missing.join(", "),
+                format_duration(ENTROPY_TIMEOUT.as_secs()),
+                room.game.get_current_turn().map_or_else(String::new, |turn| format!(" {} can roll the dice.", turn)),
+            );
+            broadcast_message(&notice, None, &mut room.clients);
+        }
+    }
+
+    fn room_of(&self, client: ClientId) -> Option<usize> {
+        self.rooms.iter().position(|room| room.clients.contains(client))
+    }
+
+    /// The client set a client currently belongs to, for direct replies.
+    fn clients_of(&mut self, client: ClientId) -> &mut Clients {
+        match self.room_of(client) {
+            Some(index) => &mut self.rooms[index].clients,
+            None => &mut self.waiting,
+        }
+    }
+
+    /// What `client` may do next, from where it is.
+    fn session(&self, client: ClientId) -> Session {
+        let Some(index) = self.room_of(client) else {
+            return Session::Connected;
+        };
+        let game = &self.rooms[index].game;
+        let Some((name, player)) = game.get_player_by_client(client) else {
+            return Session::Spectating;
+        };
+        let name = name.clone();
+        if game.is_in_progress() || game.is_over() {
+            Session::Playing { name }
+        } else if *player.color() == Color::Unassigned {
+            Session::Joined { name }
+        } else {
+            Session::ColorChosen { name }
+        }
+    }
+
+    fn handle_line(&mut self, client: ClientId, message: &str) {
+        let command = message.split_whitespace().next();
+        if let Err(refusal) = self.session(client).allows(command.unwrap_or_default()) {
+            send_message(self.clients_of(client), client, &format!("{}\nEND_OF_MESSAGE\n", refusal));
+            return;
+        }
+        if matches!(command, Some("REGISTER" | "LOGIN" | "NAME")) {
+            self.account_command(client, message);
+            return;
+        }
+        if matches!(command, Some("STATS" | "HISTORY" | "LEADERBOARD")) {
+            self.stats_command(client, message);
+            return;
+        }
+        if command == Some("DICE_STATS") {
+            self.dice_stats_command(client, message);
+            return;
+        }
+
+        // Logged-in players always join under their display name, and guests
+        // cannot take a name that belongs to an account.
+        let join_message;
+        let message = if command == Some("JOIN") && self.room_of(client).is_none() {
+            match self.join_message(client, message) {
+                Some(rewritten) => {
+                    join_message = rewritten;
+                    join_message.as_str()
+                }
+                None => return,
+            }
+        } else {
+            message
+        };
+
+        let index = match self.room_of(client) {
+            Some(index) if command == Some("WHO") => {
+                let room = &mut self.rooms[index];
+                let who_message = room.who_message();
+                send_message(&mut room.clients, client, &who_message);
+                return;
+            }
+            Some(index) if command == Some("SAY") || command == Some("WHISPER") => {
+                self.chat(index, client, message);
+                return;
+            }
+            Some(index) if matches!(command, Some("LEAVE" | "REMATCH" | "END" | "KICK" | "HOST")) => {
+                self.table_command(index, client, message);
+                return;
+            }
+            Some(index) if command == Some("SAVE") => {
+                self.rooms[index].save_game(client, &self.save_dir);
+                return;
+            }
+            Some(index) => index,
+            None if command == Some("WATCH") => {
+                self.watch(client, message);
+                return;
+            }
+            None if command == Some("RESUME") => {
+                self.resume(client, message);
+                return;
+            }
+            None if command == Some("JOIN") && self.claim_seat(client, message) => return,
+            None if command == Some("JOIN") => match self.open_room() {
+                Some(index) => {
+                    if let Some(outbound) = self.waiting.take(client) {
+                        self.rooms[index].clients.add(client, outbound);
+                    }
+                    index
+                }
+                None => {
+                    let response = "All rooms are full. Please try again later.\nEND_OF_MESSAGE\n";
+                    send_message(&mut self.waiting, client, response);
+                    return;
+                }
+            },
+            None => {
+                let response = "Join a game first with 'JOIN <name>', or watch one with 'WATCH [room]'.\nEND_OF_MESSAGE\n";
+                send_message(&mut self.waiting, client, response);
+                return;
+            }
+        };
+
+        let room = &mut self.rooms[index];
+        if let Err(e) = handle_message(message, client, &mut room.game, &mut room.clients) {
+            eprintln!("Error handling '{}' from {} in room {}: {}", message, client, room.id, e);
+        }
+        self.link_account(index, client, command);
+
+        let room = &mut self.rooms[index];
+        room.broadcast_state_if_changed();
+        if room.started_at.is_none() && room.game.is_in_progress() {
+            room.started_at = Some(Instant::now());
+            room.log_start();
+        }
+        if room.game.is_over() && !room.replay_saved {
+            let replay = room.save_replay(&self.replay_dir);
+            room.journal.remove();
+            let record = room.game_record(replay);
+            if let Err(e) = self.accounts.record_game(&record) {
+                eprintln!("Could not record the result of room {}: {}", record.room_id, e);
+            }
+            room.audit_dice();
+            match self.accounts.dice_histogram(None) {
+                Ok(histogram) => audit_dice("all finished games", &histogram),
+                Err(e) => eprintln!("Could not audit the dice of all finished games: {}", e),
+            }
+        }
+
+        // A rejected JOIN leaves the client without a seat; send it back to the lobby.
+        if !room.game.has_client(client) {
+            if let Some(outbound) = room.clients.take(client) {
+                self.waiting.add(client, outbound);
+            }
+        }
+    }
+
+    /// `LEAVE` frees the client's seat, or stops it watching, and sends it back
+    /// to the lobby; players can only leave before the game starts or once it
+    /// is over. Before the start the host can `KICK <player>` and hand the role
+    /// over with `HOST <player>`. After the game, `REMATCH` starts a new one
+    /// once every player still at the table asked for it, and `END` closes the room.
+    fn table_command(&mut self, index: usize, client: ClientId, message: &str) {
+        let parts: Vec<&str> = message.split_whitespace().collect();
+        let command = parts.first().copied().unwrap_or_default();
+        let room = &mut self.rooms[index];
+        let seat = room.game.get_player_by_client(client).map(|(name, _)| name.clone());
+
+        match (command, seat) {
+            ("LEAVE", None) => {
+                room.spectators.remove(&client);
+                let notice = format!("You stopped watching room {}.", room.id);
+                self.return_to_lobby(index, client, &notice);
+            }
+            ("LEAVE", Some(_)) if room.game.is_in_progress() => {
+                let response = "You can leave once the game is over.\nEND_OF_MESSAGE\n";
+                send_message(&mut room.clients, client, response);
+            }
+            ("LEAVE", Some(name)) => {
+                if room.game.is_over() {
+                    if let Some(player) = room.game.get_player_by_client_mut(client) {
+                        player.client_id = ClientId::UNCLAIMED;
+                    }
+                    room.rematch_votes.remove(&client);
+                } else {
+                    room.game.remove_player(&name);
+                }
+                println!("{} left room {}", name, room.id);
+                broadcast_message(&format!("{} left the table.\nEND_OF_MESSAGE\n", name), Some(client), &mut room.clients);
+                let notice = format!("You left room {}.", room.id);
+                self.return_to_lobby(index, client, &notice);
+
+                let room = &mut self.rooms[index];
+                room.broadcast_state_if_changed();
+                if room.game.is_over() {
+                    room.replace_absent_host();
+                    room.start_rematch_if_agreed(&self.data_dir, &mut self.dice);
+                }
+            }
+            (_, None) => {
+                let response = "Spectators cannot play. Type 'LEAVE' to stop watching.\nEND_OF_MESSAGE\n";
+                send_message(&mut room.clients, client, response);
+            }
+            ("KICK" | "HOST", Some(name)) => {
+                let target = parts.get(1).map(|target| player_id(target));
+                let response = match target {
+                    _ if room.game.is_in_progress() || room.game.is_over() => "The host can only change the table before the game starts.".to_string(),
+                    _ if room.game.host() != Some(&name) => format!("Only the host ({}) can do that.", room.game.host().map_or("nobody", |host| host.as_str())),
+                    None => format!("Usage: {} <player>", command),
+                    Some(target) if target == name => "You cannot do that to yourself.".to_string(),
+                    Some(target) if room.game.get_player(&target).is_none() => format!("There is no player called {} at this table.", target),
+                    Some(target) if command == "HOST" => {
+                        room.game.set_host(&target);
+                        println!("{} handed the host role in room {} to {}", name, room.id, target);
+                        broadcast_message(&format!("{} is now the host.\nEND_OF_MESSAGE\n", target), None, &mut room.clients);
+                        return;
+                    }
+                    Some(target) => {
+                        let kicked = room.game.get_player(&target).map(|player| player.client_id).unwrap_or(ClientId::UNCLAIMED);
+                        room.game.remove_player(&target);
+                        println!("{} removed {} from room {}", name, target, room.id);
+                        let notice = format!("{} was removed from the table by {}.\nEND_OF_MESSAGE\n", target, name);
+                        broadcast_message(&notice, Some(kicked), &mut room.clients);
+                        let notice = format!("The host removed you from room {}.", room.id);
+                        self.return_to_lobby(index, kicked, &notice);
+                        self.rooms[index].broadcast_state_if_changed();
+                        return;
+                    }
+                };
+                send_message(&mut room.clients, client, &format!("{}\nEND_OF_MESSAGE\n", response));
+            }
+            (_, Some(_)) if !room.game.is_over() => {
+                let response = format!("'{}' is only available once the game is over.\nEND_OF_MESSAGE\n", command);
+                send_message(&mut room.clients, client, &response);
+            }
+            ("REMATCH", Some(name)) => {
+                room.rematch_votes.insert(client);
+                let notice = format!("{} wants a rematch.\nEND_OF_MESSAGE\n", name);
+                broadcast_message(&notice, None, &mut room.clients);
+                room.start_rematch_if_agreed(&self.data_dir, &mut self.dice);
+            }
+            (_, Some(name)) if room.game.host() != Some(&name) => {
+                let host = room.game.host().map_or("nobody", |host| host.as_str());
+                let response = format!("Only the host ({}) can close the room; type 'LEAVE' to go back to the lobby.\nEND_OF_MESSAGE\n", host);
+                send_message(&mut room.clients, client, &response);
+            }
+            (_, Some(name)) => {
+                println!("{} closed room {}", name, room.id);
+                let notice = format!("{} closed room {}.", name, room.id);
+                for other in room.clients.ids() {
+                    self.return_to_lobby(index, other, &notice);
+                }
+                self.rooms.remove(index);
+            }
+        }
+    }
+
+    /// Moves `client` from a room back to the waiting lobby.
+    fn return_to_lobby(&mut self, index: usize, client: ClientId, notice: &str) {
+        let Some(outbound) = self.rooms[index].clients.take(client) else {
+            return;
+        };
+        self.waiting.add(client, outbound);
+        let message = format!("{}\nEnter 'JOIN <name>' to join a game or 'WATCH [room]' to watch one.\nEND_OF_MESSAGE\n", notice);
+        send_message(&mut self.waiting, client, &message);
+    }
+
+    /// Returns a room that still accepts players, opening a new one if allowed.
+    fn open_room(&mut self) -> Option<usize> {
+        if let Some(index) = self.rooms.iter().position(|room| room.game.can_add_player()) {
+            return Some(index);
+        }
+        if self.rooms.len() >= self.max_rooms {
+            return None;
+        }
+
+        let mut game = Game::new(self.rules.clone());
+        self.dice.deal(&mut game);
+        Some(self.add_room(game))
+    }
+
+    fn add_room(&mut self, game: Game) -> usize {
+        let journal = Journal::new(&self.data_dir, self.next_room_id);
+        self.insert_room(self.next_room_id, game, journal)
+    }
+
+    fn insert_room(&mut self, id: usize, game: Game, journal: Journal) -> usize {
+        println!("Opening room {}", id);
+        // A restored game's real start is unknown; count from when it came back.
+        let started_at = game.is_in_progress().then(Instant::now);
+        self.rooms.push(Room {
+            id,
+            game,
+            clients: Clients::new(),
+            spectators: HashSet::new(),
+            last_state: String::new(),
+            empty_since: None,
+            started_at,
+            replay_saved: false,
+            rematch_votes: HashSet::new(),
+            journal,
+        });
+        self.next_room_id = self.next_room_id.max(id + 1);
+        self.rooms.len() - 1
+    }
+
+    /// Seats `client` as a spectator of the requested room, or of the liveliest one.
+    fn watch(&mut self, client: ClientId, message: &str) {
+        let requested = message.split_whitespace().nth(1);
+        let index = match requested {
+            Some(room_id) => self.rooms.iter().position(|room| room_id.parse() == Ok(room.id)),
+            None => self.rooms.iter().position(|room| room.game.is_in_progress())
+                .or_else(|| (!self.rooms.is_empty()).then_some(0)),
+        };
+
+        let Some(index) = index else {
+            let response = format!("There is no such game to watch.\n{}\nEND_OF_MESSAGE\n", self.rooms_message());
+            send_message(&mut self.waiting, client, &response);
+            return;
+        };
+        let Some(outbound) = self.waiting.take(client) else {
+            return;
+        };
+
+        let room = &mut self.rooms[index];
+        room.clients.add(client, outbound);
+        room.spectators.insert(client);
+
+        let snapshot = format!("You are watching room {}.\n{}END_OF_MESSAGE\n", room.id, room.game.get_snapshot_message());
+        send_message(&mut room.clients, client, &snapshot);
+        let state = format!("{}\nEND_OF_MESSAGE\n", room.game.get_state_line());
+        send_message(&mut room.clients, client, &state);
+    }
+
+    fn rooms_message(&self) -> String {
+        if self.rooms.is_empty() {
+            return String::from("No games are being played right now.");
+        }
+
+        let mut message = String::from("Rooms:");
+        for room in &self.rooms {
+            let status = if room.game.is_in_progress() { "in progress" } else { "not started" };
+            message.push_str(&format!("\n-> Room {}: {} players, {}", room.id, room.game.num_players(), status));
+        }
+        message
+    }
+
+    /// Relays `SAY <text>` to the whole room or `WHISPER <player> <text>` to one player.
+    fn chat(&mut self, index: usize, client: ClientId, message: &str) {
+        let room = &mut self.rooms[index];
+        let (command, rest) = message.split_once(char::is_whitespace).unwrap_or((message, ""));
+
+        let (recipient, text) = if command == "WHISPER" {
+            match rest.trim_start().split_once(char::is_whitespace) {
+                Some((target, text)) => match room.game.get_player(target) {
+                    Some(player) if room.clients.contains(player.client_id) => (Some((player.client_id, target)), text),
+                    Some(_) => {
+                        let response = format!("{} is not connected.\nEND_OF_MESSAGE\n", target);
+                        send_message(&mut room.clients, client, &response);
+                        return;
+                    }
+                    None => {
+                        let response = format!("There is no player named '{}' at this table.\nEND_OF_MESSAGE\n", target);
+                        send_message(&mut room.clients, client, &response);
+                        return;
+                    }
+                },
+                None => {
+                    let response = "Usage: WHISPER <player> <message>\nEND_OF_MESSAGE\n";
+                    send_message(&mut room.clients, client, response);
+                    return;
+                }
+            }
+        } else {
+            (None, rest)
+        };
+
+        let text = match self.chat_filter.check(client, text) {
+            Ok(text) => text,
+            Err(reason) => {
+                send_message(&mut room.clients, client, &format!("{}\nEND_OF_MESSAGE\n", reason));
+                return;
+            }
+        };
+
+        let sender = room.chat_name(client);
+        match recipient {
+            Some((recipient, target)) => {
+                let whisper = format!("[{} whispers] {}\nEND_OF_MESSAGE\n", sender, text);
+                send_message(&mut room.clients, recipient, &whisper);
+                let echo = format!("[to {}] {}\nEND_OF_MESSAGE\n", target, text);
+                send_message(&mut room.clients, client, &echo);
+            }
+            None => {
+                let chat_message = format!("[{}] {}\nEND_OF_MESSAGE\n", sender, text);
+                broadcast_message(&chat_message, None, &mut room.clients);
+            }
+        }
+    }
+
+    /// Gives a disconnected player its seat back: `RESUME <token>`.
+    fn resume(&mut self, client: ClientId, message: &str) {
+        let Some(token) = message.split_whitespace().nth(1) else {
+            let response = "Usage: RESUME <token>\nEND_OF_MESSAGE\n";
+            send_message(&mut self.waiting, client, response);
+            return;
+        };
+
+        let resumed = self.rooms.iter_mut()
+            .find_map(|room| room.game.resume_player(token, client).map(|seat| (room, seat)));
+        let Some((room, (name, previous))) = resumed else {
+            let response = "Unknown or expired session token.\nEND_OF_MESSAGE\n";
+            send_message(&mut self.waiting, client, response);
+            return;
+        };
+        let Some(outbound) = self.waiting.take(client) else {
+            return;
+        };
+
+        // The old connection may not have noticed it is dead yet.
+        room.clients.remove(previous);
+        room.clients.add(client, outbound);
+        println!("{} resumed {}'s seat in room {}", client, name, room.id);
+        room.welcome_back(client, &name);
+    }
+
+    /// Hands a seat of a loaded game back to the player who `JOIN`s with its name.
+    /// Returns false when there is no such unclaimed seat.
+    fn claim_seat(&mut self, client: ClientId, message: &str) -> bool {
+        let Some(name) = message.split_whitespace().nth(1) else {
+            return false;
+        };
+        let claimed = self.rooms.iter_mut()
+            .find_map(|room| room.game.claim_seat(name, client).map(|_| room));
+        let Some(room) = claimed else {
+            return false;
+        };
+        let Some(outbound) = self.waiting.take(client) else {
+            return true;
+        };
+
+        room.clients.add(client, outbound);
+        println!("{} claimed {}'s seat in room {}", client, name, room.id);
+        room.welcome_back(client, &player_id(name));
+        true
+    }
+
+    /// `REGISTER <username> <password>`, `LOGIN <username> <password>` and
+    /// `NAME <display name>` for logged-in players.
+    fn account_command(&mut self, client: ClientId, message: &str) {
+        let parts: Vec<&str> = message.split_whitespace().collect();
+        let at_table = self.room_of(client).is_some();
+
+        let result = match (parts.as_slice(), self.logins.get_mut(&client)) {
+            (["NAME", display_name], Some(account)) => match validate_name(display_name) {
+                Ok(()) => self.accounts.set_display_name(account, display_name)
+                    .map(|()| format!("Your display name is now {}.", display_name)),
+                Err(e) => Ok(e.to_string()),
+            },
+            (["NAME", ..], Some(_)) => Ok("Usage: NAME <display name>".to_string()),
+            (["NAME", ..], None) => Ok("Log in first with 'LOGIN <username> <password>'.".to_string()),
+            (_, Some(account)) => Ok(format!("You are already logged in as {}.", account.username)),
+            _ if self.pending_logins.contains_key(&client) => Ok("Your last login is still being checked.".to_string()),
+            _ if at_table => Ok("Log in before joining a table.".to_string()),
+            (["REGISTER", username, password], None) => match validate_name(username) {
+                Ok(()) => return self.check_account(client, username, password, AccountStore::register),
+                Err(e) => Ok(e.to_string()),
+            },
+            (["LOGIN", username, password], None) => match self.login_lockout(username) {
+                Some(remaining) => Ok(format!("Too many failed logins for {}; try again in {}.", username, format_duration(remaining.as_secs() + 1))),
+                None => return self.check_account(client, username, password, AccountStore::login),
+            },
+            _ => Ok("Usage: REGISTER <username> <password> or LOGIN <username> <password>".to_string()),
+        };
+        self.send_account_response(client, result);
+    }
+
+    fn send_account_response(&mut self, client: ClientId, result: Result<String, AccountError>) {
+        let response = match result {
+            Ok(response) => response,
+            Err(AccountError::Database(e)) => {
+                eprintln!("Account database error for {}: {}", client, e);
+                "Accounts are unavailable right now.".to_string()
+            }
+            Err(e) => e.to_string(),
+        };
+        send_message(self.clients_of(client), client, &format!("{}\nEND_OF_MESSAGE\n", response));
+    }
+
+    /// Runs a `REGISTER` or `LOGIN` on a blocking thread with its own database
+    /// connection, so hashing the password does not hold up every room; the
+    /// outcome comes back as `ClientEvent::AccountChecked`.
+    fn check_account(
+        &mut self,
+        client: ClientId,
+        username: &str,
+        password: &str,
+        check: fn(&AccountStore, &str, &str) -> Result<Account, AccountError>,
+    ) {
+        let accounts = match self.accounts.reopen() {
+            Ok(accounts) => accounts,
+            Err(e) => return self.send_account_response(client, Err(e)),
+        };
+        self.pending_logins.insert(client, username.to_lowercase());
+        let commands = self.commands.clone();
+        let (username, password) = (username.to_string(), password.to_string());
+        tokio::task::spawn_blocking(move || {
+            let result = check(&accounts, &username, &password);
+            let _ = commands.blocking_send(GameCommand { client, event: ClientEvent::AccountChecked(result) });
+        });
+    }
+
+    fn account_checked(&mut self, client: ClientId, result: Result<Account, AccountError>) {
+        // Gone since asking.
+        let Some(username) = self.pending_logins.remove(&client) else {
+            return;
+        };
+        let result = match result {
+            Ok(account) if self.logins.values().any(|other| other.id == account.id) => {
+                Ok(format!("{} is already logged in on another connection.", account.username))
+            }
+            Ok(_) if self.room_of(client).is_some() => Ok("Log in before joining a table.".to_string()),
+            Ok(account) => {
+                self.failed_logins.remove(&username);
+                Ok(self.log_in(client, account))
+            }
+            Err(AccountError::InvalidCredentials) => {
+                let failures = self.failed_logins.entry(username).or_insert((0, Instant::now()));
+                *failures = (failures.0 + 1, Instant::now());
+                Err(AccountError::InvalidCredentials)
+            }
+            Err(e) => Err(e),
+        };
+        self.send_account_response(client, result);
+    }
+
+    /// How much longer `username` is locked after too many failed logins, if it is.
+    fn login_lockout(&mut self, username: &str) -> Option<Duration> {
+        let now = Instant::now();
+        self.failed_logins.retain(|_, (_, last)| now.duration_since(*last) < LOGIN_LOCKOUT);
+        let (failures, last) = self.failed_logins.get(&username.to_lowercase())?;
+        (*failures >= MAX_FAILED_LOGINS).then(|| LOGIN_LOCKOUT - now.duration_since(*last))
+    }
+
+    /// `STATS [player]` and `HISTORY`, for the client's own account unless
+    /// another player is named, and `LEADERBOARD`.
+    fn stats_command(&mut self, client: ClientId, message: &str) {
+        let parts: Vec<&str> = message.split_whitespace().collect();
+        let own = self.logins.get(&client).cloned();
+
+        let result = match (parts.as_slice(), own) {
+            (["STATS", name], _) => self.accounts.find_account(name).and_then(|account| match account {
+                Some(account) => self.stats_message(&account),
+                None => Ok(format!("There is no registered player called {}.", name)),
+            }),
+            (["STATS"], Some(account)) => self.stats_message(&account),
+            (["STATS"], None) => Ok("Usage: STATS <player>, or log in to see your own.".to_string()),
+            (["HISTORY"], own) => self.history_message(own.as_ref()),
+            (["LEADERBOARD"], _) => self.leaderboard_message(),
+            _ => Ok("Usage: STATS [player], HISTORY or LEADERBOARD".to_string()),
+        };
+
+        let response = match result {
+            Ok(response) => response,
+            Err(e) => {
+                eprintln!("Account database error for {}: {}", client, e);
+                "Stats are unavailable right now.".to_string()
+            }
+        };
+        send_message(self.clients_of(client), client, &format!("{}\nEND_OF_MESSAGE\n", response));
+    }
+
+    /// `DICE_STATS [player]`: how the dice came out in the client's game, in
+    /// its own finished games and in every finished game, or for one
+    /// registered player across their games.
+    fn dice_stats_command(&mut self, client: ClientId, message: &str) {
+        let parts: Vec<&str> = message.split_whitespace().collect();
+        let result = match parts.as_slice() {
+            ["DICE_STATS"] => self.dice_stats_message(client),
+            ["DICE_STATS", name] => self.accounts.find_account(name).and_then(|account| match account {
+                Some(account) => self.accounts.dice_histogram(Some(account.id))
+                    .map(|histogram| dice_line(&format!("{} in finished games", account.display_name), &histogram)),
+                None => Ok(format!("There is no registered player called {}.", name)),
+            }),
+            _ => Ok("Usage: DICE_STATS [player]".to_string()),
+        };
+
+        let response = match result {
+            Ok(response) => response,
+            Err(e) => {
+                eprintln!("Account database error for {}: {}", client, e);
+                "Dice stats are unavailable right now.".to_string()
+            }
+        };
+        send_message(self.clients_of(client), client, &format!("{}\nEND_OF_MESSAGE\n", response));
+    }
+
+    fn dice_stats_message(&self, client: ClientId) -> Result<String, AccountError> {
+        let mut lines = Vec::new();
+        if let Some(index) = self.room_of(client) {
+            let game = &self.rooms[index].game;
+            lines.push(dice_line("This game", &game.total_dice_histogram()));
+            for name in game.standings() {
+                lines.push(dice_line(&format!("  {}", name), &game.dice_histogram(&name)));
+            }
+        }
+        if let Some(account) = self.logins.get(&client) {
+            lines.push(dice_line("Your finished games", &self.accounts.dice_histogram(Some(account.id))?));
+        }
+        lines.push(dice_line("All finished games", &self.accounts.dice_histogram(None)?));
+        Ok(lines.join("\n"))
+    }
+
+    fn stats_message(&self, account: &Account) -> Result<String, AccountError> {
+        let stats = self.accounts.stats(account.id)?;
+        if stats.games == 0 {
+            return Ok(format!("{} has not finished a game yet.", account.display_name));
+        }
+        Ok(format!(
+            "Stats for {}\nRating: {:.0}\nGames played: {}\nWins: {}\nAverage place: {:.1}\nSixes rolled: {}\nAverage game length: {}",
+            account.display_name,
+            stats.rating,
+            stats.games,
+            stats.wins,
+            stats.average_position,
+            stats.sixes,
+            format_duration(stats.average_duration_secs as u64),
+        ))
+    }
+
+    /// The last games the account played, or the last games on the server for guests.
+    fn history_message(&self, account: Option<&Account>) -> Result<String, AccountError> {
+        let entries = self.accounts.history(account.map(|account| account.id), HISTORY_LENGTH)?;
+        if entries.is_empty() {
+            return Ok("No finished games yet.".to_string());
+        }
+
+        let now = SystemTime::now().duration_since(UNIX_EPOCH).map_or(0, |elapsed| elapsed.as_secs() as i64);
+        let mut message = match account {
+            Some(account) => format!("Recent games of {}:", account.display_name),
+            None => "Recent games:".to_string(),
+        };
+        for entry in entries {
+            let place = entry.position.map_or(String::new(), |position| format!(", you placed {}/{}", position, entry.players));
+            let replay = entry.replay.map_or(String::new(), |replay| format!(" -- replay: {}", replay));
+            message.push_str(&format!(
+                "\n#{} {} ago, room {}: {} won ({} players{}){}",
+                entry.game_id,
+                format_duration(now.saturating_sub(entry.finished_at) as u64),
+                entry.room_id,
+                entry.winner,
+                entry.players,
+                place,
+                replay,
+            ));
+        }
+        Ok(message)
+    }
+
+    fn leaderboard_message(&self) -> Result<String, AccountError> {
+        let entries = self.accounts.leaderboard(LEADERBOARD_LENGTH)?;
+        if entries.is_empty() {
+            return Ok("Nobody is rated yet. Ratings count games between two or more registered players.".to_string());
+        }
+
+        let mut message = "Leaderboard:".to_string();
+        for (place, entry) in entries.iter().enumerate() {
+            message.push_str(&format!("\n{}. {} {:.0} ({} played)", place + 1, entry.display_name, entry.rating, entry.games));
+        }
+        Ok(message)
+    }
+
+    fn log_in(&mut self, client: ClientId, account: Account) -> String {
+        println!("{} logged in as {} (account {})", client, account.username, account.id);
+        let response = format!("Welcome, {}! Type 'JOIN' to take a seat as {}.", account.username, account.display_name);
+        self.logins.insert(client, account);
+        response
+    }
+
+    /// The `JOIN` to play for `client`, or `None` if it was refused.
+    fn join_message(&mut self, client: ClientId, message: &str) -> Option<String> {
+        if let Some(account) = self.logins.get(&client) {
+            return Some(format!("JOIN {}", account.display_name));
+        }
+        if self.pending_logins.contains_key(&client) {
+            send_message(&mut self.waiting, client, "Wait until your login has been checked before joining.\nEND_OF_MESSAGE\n");
+            return None;
+        }
+
+        let Some(name) = message.split_whitespace().nth(1) else {
+            return Some(message.to_string());
+        };
+        match self.accounts.is_name_taken(name) {
+            Ok(false) => Some(message.to_string()),
+            Ok(true) => {
+                let response = format!("The name {} belongs to a registered player. Use 'LOGIN' to play as them.\nEND_OF_MESSAGE\n", name);
+                send_message(&mut self.waiting, client, &response);
+                None
+            }
+            Err(e) => {
+                eprintln!("Account database error for {}: {}", client, e);
+                Some(message.to_string())
+            }
+        }
+    }
+
+    /// Ties a logged-in client's seat to its account: records the account on
+    /// `JOIN`, picks the preferred color when it is free, and remembers colors chosen.
+    fn link_account(&mut self, index: usize, client: ClientId, command: Option<&str>) {
+        let Some(account) = self.logins.get_mut(&client) else {
+            return;
+        };
+        let room = &mut self.rooms[index];
+        let Some((_, player)) = room.game.get_player_by_client(client) else {
+            return;
+        };
+        let color = format!("{:?}", player.color());
+
+        match command {
+            Some("JOIN") => {
+                if let Some(player) = room.game.get_player_by_client_mut(client) {
+                    player.account_id = Some(account.id);
+                }
+                let preferred = account.preferred_color.clone()
+                    .filter(|preferred| room.game.available_colors().iter().any(|color| format!("{:?}", color) == *preferred));
+                if let Some(preferred) = preferred {
+                    if let Err(e) = handle_message(&format!("COLOR {}", preferred), client, &mut room.game, &mut room.clients) {
+                        eprintln!("Error choosing the preferred color of {}: {}", client, e);
+                    }
+                }
+            }
+            Some("COLOR") if account.preferred_color.as_deref() != Some(color.as_str()) && color != "Unassigned" => {
+                if let Err(e) = self.accounts.set_preferred_color(account, &color) {
+                    eprintln!("Could not store the preferred color of account {}: {}", account.id, e);
+                }
+            }
+            _ => {}
+        }
+    }
+
+    fn disconnect(&mut self, client: ClientId) {
+        self.chat_filter.forget(client);
+        self.logins.remove(&client);
+        self.pending_logins.remove(&client);
+        self.waiting.remove(client);
+        for room in &mut self.rooms {
+            // A client whose queue filled up is already gone from `clients`, but still holds its seat.
+            room.clients.remove(client);
+            room.spectators.remove(&client);
+
+            let Some((name, _)) = room.game.get_player_by_client(client) else {
+                continue;
+            };
+            let name = name.clone();
+            if room.game.is_over() {
+                // Nothing to come back to; the others can rematch without them.
+                if let Some(player) = room.game.get_player_by_client_mut(client) {
+                    player.client_id = ClientId::UNCLAIMED;
+                }
+                room.rematch_votes.remove(&client);
+                broadcast_message(&format!("{} left the table.\nEND_OF_MESSAGE\n", name), None, &mut room.clients);
+                room.replace_absent_host();
+                room.start_rematch_if_agreed(&self.data_dir, &mut self.dice);
+            } else if room.game.is_in_progress() {
+                let notice = format!("{} lost connection. Their seat is kept until they reconnect.\nEND_OF_MESSAGE\n", name);
+                broadcast_message(&notice, None, &mut room.clients);
+            } else {
+                // Before the start a missing player would hold everyone up, and a missing host would block BEGIN.
+                let host = room.game.host().cloned();
+                room.game.remove_player(&name);
+                println!("{} lost connection and left room {}", name, room.id);
+                broadcast_message(&format!("{} lost connection and left the table.\nEND_OF_MESSAGE\n", name), None, &mut room.clients);
+                match room.game.host() {
+                    Some(new_host) if host.as_ref() != Some(new_host) => {
+                        let notice = format!("{} is now the host.\nEND_OF_MESSAGE\n", new_host);
+                        broadcast_message(&notice, None, &mut room.clients);
+                    }
+                    _ => {}
+                }
+                room.broadcast_state_if_changed();
+            }
+        }
+    }
+
+    /// Closes rooms nobody is connected to. Games in progress are kept for
+    /// `RESUME_GRACE` so their players can reconnect.
+    fn close_empty_rooms(&mut self) {
+        let now = Instant::now();
+        self.rooms.retain_mut(|room| {
+            if !room.clients.is_empty() {
+                room.empty_since = None;
+                return true;
+            }
+
+            let empty_since = *room.empty_since.get_or_insert(now);
+            // A loaded game nobody has come back to yet waits for as long as it takes.
+            let abandoned = room.game.unclaimed_seats().len() == room.game.num_players();
+            let keep = room.game.is_in_progress() && (abandoned || now.duration_since(empty_since) < RESUME_GRACE);
+            if !keep {
+                println!("Closing room {}", room.id);
+                room.journal.remove();
+            }
+            keep
+        });
+    }
+}
+
+impl Room {
+    /// Passes the host role on once the host is gone, and tells the table.
+    fn replace_absent_host(&mut self) {
+        if let Some(host) = self.game.replace_absent_host() {
+            println!("{} is now the host of room {}", host, self.id);
+            broadcast_message(&format!("{} is now the host.\nEND_OF_MESSAGE\n", host), None, &mut self.clients);
+        }
+    }
+
+    /// Catches a returning player up and tells the table; once the last seat of
+    /// a loaded game is taken back, play resumes.
+    fn welcome_back(&mut self, client: ClientId, name: &str) {
+        if let Some(player) = self.game.get_player(name) {
+            send_message(&mut self.clients, client, &token_message(player.session_token(), player.name()));
+        }
+        // Enough for the client to check the fair dice, and to add its entropy if it still owes it.
+        if self.game.dice_commitment().is_some() {
+            for event in self.game.events().iter().filter(|event| matches!(event, GameEvent::Committed { .. } | GameEvent::Entropy { .. } | GameEvent::Rolled { .. })) {
+                send_message(&mut self.clients, client, &format!("DICE {}\nEND_OF_MESSAGE\n", event));
+            }
+        }
+        let welcome_back = format!("Welcome back, {}! You are in room {}.\n{}END_OF_MESSAGE\n", name, self.id, self.game.get_snapshot_message());
+        send_message(&mut self.clients, client, &welcome_back);
+        let state = format!("{}\nEND_OF_MESSAGE\n", self.game.get_state_line());
+        send_message(&mut self.clients, client, &state);
+        broadcast_message(&format!("{} reconnected.\nEND_OF_MESSAGE\n", name), Some(client), &mut self.clients);
+
+        let unclaimed = self.game.unclaimed_seats();
+        if !unclaimed.is_empty() {
+            let notice = format!("Waiting for {} to take their seats again.\nEND_OF_MESSAGE\n", unclaimed.join(", "));
+            broadcast_message(&notice, None, &mut self.clients);
+        } else if let (true, Some(turn)) = (self.game.is_in_progress(), self.game.get_current_turn()) {
+            let notice = format!("Everyone is at the table. It's {}'s turn.\nEND_OF_MESSAGE\n", turn);
+            broadcast_message(&notice, None, &mut self.clients);
+        }
+    }
+
+    /// Replaces the finished game with a new one for the same players once
+    /// everyone still seated asked for a rematch.
+    fn start_rematch_if_agreed(&mut self, data_dir: &Path, dice: &mut DiceDealer) {
+        let seated: Vec<ClientId> = self.game.iter_players()
+            .map(|(_, player)| player.client_id)
+            .filter(|client| *client != ClientId::UNCLAIMED)
+            .collect();
+        if self.rematch_votes.is_empty() || !seated.iter().all(|client| self.rematch_votes.contains(client)) {
+            return;
+        }
+        if seated.len() < self.game.rules().min_players {
+            let notice = "Not enough players are left for a rematch. Type 'LEAVE' to find another table.\nEND_OF_MESSAGE\n";
+            broadcast_message(notice, None, &mut self.clients);
+            return;
+        }
+
+        println!("Rematch in room {}", self.id);
+        let (mut game, tokens) = self.game.rematch();
+        dice.deal(&mut game);
+        self.game = game;
+        self.rematch_votes.clear();
+        self.replay_saved = false;
+        self.started_at = None;
+        self.journal = Journal::new(data_dir, self.id);
+        for (client, token) in &tokens {
+            if let Some((_, player)) = self.game.get_player_by_client(*client) {
+                send_message(&mut self.clients, *client, &token_message(token, player.name()));
+            }
+        }
+        broadcast_message("Rematch! Same players, same colors.\nEND_OF_MESSAGE\n", None, &mut self.clients);
+        let host = self.game.host().and_then(|host| self.game.get_player(host)).map(|player| player.client_id);
+        if let Some(host) = host {
+            if let Err(e) = handle_message("BEGIN", host, &mut self.game, &mut self.clients) {
+                eprintln!("Error starting the rematch in room {}: {}", self.id, e);
+            }
+        }
+        self.started_at = Some(Instant::now());
+        self.log_start();
+        self.broadcast_state_if_changed();
+    }
+
+    /// Logs the dice seed, so the game can be played again from it, or the
+    /// commitment of fair dice.
+    fn log_start(&self) {
+        match (self.game.dice_seed(), self.game.dice_commitment()) {
+            (Some(seed), _) => println!("Room {} started with dice seed {}", self.id, seed),
+            (None, Some(commitment)) => println!("Room {} started with fair dice committed to {}", self.id, commitment),
+            (None, None) => println!("Room {} started with scripted dice", self.id),
+        }
+    }
+
+    /// `SAVE`, for the host only: writes the game to `<dir>/room<id>.save`, over
+    /// the room's last save, so it can be continued later with `--load`.
+    fn save_game(&mut self, client: ClientId, dir: &Path) {
+        if !self.game.is_in_progress() {
+            send_message(&mut self.clients, client, "Only a game in progress can be saved.\nEND_OF_MESSAGE\n");
+            return;
+        }
+        let player = self.game.get_player_by_client(client).map(|(name, _)| name);
+        if let (Some(host), false) = (self.game.host(), player == self.game.host()) {
+            let response = format!("Only the host ({}) can save the game.\nEND_OF_MESSAGE\n", host);
+            send_message(&mut self.clients, client, &response);
+            return;
+        }
+
+        let path = dir.join(format!("room{}.save", self.id));
+        match fs::create_dir_all(dir).and_then(|()| self.game.save(&path)) {
+            Ok(()) => {
+                println!("Saved room {} to {}", self.id, path.display());
+                let notice = format!("The game was saved as {}.\nEND_OF_MESSAGE\n", path.display());
+                broadcast_message(&notice, None, &mut self.clients);
+            }
+            Err(e) => {
+                eprintln!("Could not save room {} to {}: {}", self.id, path.display(), e);
+                send_message(&mut self.clients, client, "The game could not be saved.\nEND_OF_MESSAGE\n");
+            }
+        }
+    }
+
+    /// Writes the finished game's event log to `<dir>/room<id>-<unix time>.replay`
+    /// and tells the players where to find it.
+    fn save_replay(&mut self, dir: &Path) -> Option<PathBuf> {
+        self.replay_saved = true;
+        let timestamp = SystemTime::now().duration_since(UNIX_EPOCH).map_or(0, |elapsed| elapsed.as_secs());
+        let path = dir.join(format!("room{}-{}.replay", self.id, timestamp));
+
+        let replay = Replay::new(self.game.rules(), self.game.events(), self.game.dice_seed());
+        match fs::create_dir_all(dir).and_then(|()| replay.save(&path)) {
+            Ok(()) => {
+                println!("Saved replay of room {} to {}", self.id, path.display());
+                let notice = format!("The replay of this game was saved as {}.\nEND_OF_MESSAGE\n", path.display());
+                broadcast_message(&notice, None, &mut self.clients);
+                Some(path)
+            }
+            Err(e) => {
+                eprintln!("Could not save replay of room {} to {}: {}", self.id, path.display(), e);
+                None
+            }
+        }
+    }
+
+    /// The finished game's result for the stats.
+    fn game_record(&self, replay: Option<PathBuf>) -> GameRecord {
+        let events = self.game.events();
+        let seats = self.game.standings().into_iter().map(|name| {
+            let sixes = events.iter()
+                .filter(|event| matches!(event, GameEvent::Rolled { player, value: 6 } if *player == name))
+                .count();
+            let dice = self.game.dice_histogram(&name);
+            SeatRecord {
+                account_id: self.game.get_player(&name).and_then(|player| player.account_id),
+                name,
+                sixes,
+                dice,
+            }
+        }).collect();
+
+        GameRecord {
+            room_id: self.id,
+            replay: replay.map(|path| path.display().to_string()),
+            duration_secs: self.started_at.map_or(0, |started_at| started_at.elapsed().as_secs()),
+            rolls: events.iter().filter(|event| matches!(event, GameEvent::Rolled { .. })).count(),
+            seats,
+        }
+    }
+
+    /// Logs every player of the finished game, and the game as a whole, whose
+    /// rolls look unlikely from fair dice.
+    fn audit_dice(&self) {
+        for name in self.game.standings() {
+            audit_dice(&format!("room {} for {}", self.id, name), &self.game.dice_histogram(&name));
+        }
+        audit_dice(&format!("room {}", self.id), &self.game.total_dice_histogram());
+    }
+
+    fn broadcast_state_if_changed(&mut self) {
+        let state = self.game.get_state_line();
+        if state != self.last_state {
+            broadcast_message(&format!("{}\nEND_OF_MESSAGE\n", state), None, &mut self.clients);
+            self.last_state = state;
+        }
+    }
+
+    /// How a client is shown in chat: its player name and color, or its spectator tag.
+    fn chat_name(&self, client: ClientId) -> String {
+        match self.game.get_player_by_client(client) {
+            Some((name, player)) => format!("{} ({:?})", name, player.color()),
+            None => format!("spectator {}", client),
+        }
+    }
+
+    fn who_message(&self) -> String {
+        let mut message = format!("Room {}\nPlayers:\n", self.id);
+        let waiting = !self.game.is_in_progress() && !self.game.is_over();
+        for (name, player) in self.game.iter_players() {
+            let host = if self.game.host() == Some(name) { ", host" } else { "" };
+            let ready = match (waiting, player.is_ready()) {
+                (true, true) => ", ready",
+                (true, false) => ", not ready",
+                (false, _) => "",
+            };
+            message.push_str(&format!("-> {} ({:?}{}{})\n", name, player.color(), host, ready));
+        }
+
+        let mut spectators: Vec<_> = self.spectators.iter().collect();
+        spectators.sort_by_key(|client| client.0);
+        message.push_str(&format!("Spectators ({}):\n", spectators.len()));
+        for spectator in spectators {
+            message.push_str(&format!("-> spectator {}\n", spectator));
+        }
+        message.push_str("END_OF_MESSAGE\n");
+        message
+    }
+}
+
+/// `<label>: <n> rolls, 1:5 2:7 ... (chi-squared 2.3)`
+fn dice_line(label: &str, histogram: &DiceHistogram) -> String {
+    if histogram.total() == 0 {
+        return format!("{}: no rolls yet", label);
+    }
+    format!("{}: {} rolls, {} (chi-squared {:.1})", label, histogram.total(), histogram, histogram.chi_squared())
+}
+
+/// Logs `histogram` when it fails the chi-squared check against fair dice.
+fn audit_dice(scope: &str, histogram: &DiceHistogram) {
+    if histogram.is_anomalous() {
+        eprintln!(
+            "Dice anomaly in {}: {} over {} rolls, chi-squared {:.1} above {:.1}",
+            scope, histogram, histogram.total(), histogram.chi_squared(), CHI_SQUARED_CRITICAL
+        );
+    }
+}
+
+/// `75` -> `1m 15s`, for game lengths and how long ago a game ended.
+fn format_duration(secs: u64) -> String {
+    match secs {
+        0..=59 => format!("{}s", secs),
+        60..=3599 => format!("{}m {}s", secs / 60, secs % 60),
+        3600..=86399 => format!("{}h {}m", secs / 3600, secs % 3600 / 60),
+        _ => format!("{}d {}h", secs / 86400, secs % 86400 / 3600),
+    }
+}
@@ -1,32 +1,102 @@
+mod accounts;
+mod chat;
 mod client_handler;
 mod communication;
-mod game_state;
+mod config;
+mod error;
+mod game_loop;
+mod persistence;
+mod ratings;
+mod session;
 
-use std::net::{TcpListener};
-use std::thread;
-use std::sync::{Arc, Mutex};
+use tokio::net::TcpListener;
+use tokio::sync::mpsc;
+use accounts::AccountStore;
 use client_handler::handle_client;
-use game_state::Game; 
+use communication::ClientId;
+use config::{Config, USAGE};
+use game_loop::{run_game, COMMAND_QUEUE_SIZE};
+use parchis_core::game_state::Game;
+use parchis_core::rules::Rules;
 
-fn main() {
-    let listener = TcpListener::bind("127.0.0.1:7878").expect("Could not bind to port");
-    println!("Server running on port 7878");
+#[tokio::main]
+async fn main() {
+    let config = match Config::from_args(std::env::args().skip(1)) {
+        Ok(config) => config,
+        Err(e) => {
+            eprintln!("{}\n{}", e, USAGE);
+            std::process::exit(2);
+        }
+    };
+
+    let rules = match &config.rules_file {
+        Some(path) => match Rules::load(path) {
+            Ok(rules) => rules,
+            Err(e) => {
+                eprintln!("Could not load rules from {}: {}", path.display(), e);
+                std::process::exit(2);
+            }
+        },
+        None => Rules::default(),
+    };
+
+    let loaded_game = match &config.load_file {
+        Some(path) => match Game::load(path) {
+            Ok(game) => {
+                println!("Loaded saved game from {}; waiting for {} to come back", path.display(), game.unclaimed_seats().join(", "));
+                Some(game)
+            }
+            Err(e) => {
+                eprintln!("Could not load the saved game {}: {}", path.display(), e);
+                std::process::exit(2);
+            }
+        },
+        None => None,
+    };
+
+    let restored_games = match persistence::restore(&config.data_dir) {
+        Ok(restored) => restored,
+        Err(e) => {
+            eprintln!("Could not restore the games in {}: {}", config.data_dir.display(), e);
+            std::process::exit(2);
+        }
+    };
+
+    if let Err(e) = std::fs::create_dir_all(&config.data_dir) {
+        eprintln!("Could not create {}: {}", config.data_dir.display(), e);
+        std::process::exit(2);
+    }
+    let accounts_path = config.data_dir.join("accounts.db");
+    let accounts = match AccountStore::open(&accounts_path) {
+        Ok(accounts) => accounts,
+        Err(e) => {
+            eprintln!("Could not open the accounts in {}: {}", accounts_path.display(), e);
+            std::process::exit(2);
+        }
+    };
+
+    let listener = match TcpListener::bind(config.address()).await {
+        Ok(listener) => listener,
+        Err(e) => {
+            eprintln!("Could not bind to {}: {}", config.address(), e);
+            std::process::exit(2);
+        }
+    };
+    println!("Server running on {} (up to {} rooms)", config.address(), config.max_rooms);
 
-    let clients = Arc::new(Mutex::new(Vec::new()));
+    let (commands_tx, commands_rx) = mpsc::channel(COMMAND_QUEUE_SIZE);
+    tokio::spawn(run_game(commands_rx, commands_tx.clone(), rules, config, accounts, restored_games, loaded_game));
 
-    let game = Arc::new(Mutex::new(Game::new()));
+    let mut next_client_id = 0;
 
-    for stream in listener.incoming() {
-        match stream {
-            Ok(stream) => {
-                println!("New connection: {}", stream.peer_addr().unwrap());
-                
-                let clients = Arc::clone(&clients);
-                let game = Arc::clone(&game);
+    loop {
+        match listener.accept().await {
+            Ok((stream, addr)) => {
+                next_client_id += 1;
+                let client = ClientId(next_client_id);
+                println!("New connection: {} ({})", addr, client);
 
-                thread::spawn(move || {
-                    handle_client(stream, clients, game);
-                });
+                tokio::spawn(handle_client(stream, client, commands_tx.clone()));
             }
             Err(e) => {
                 eprintln!("Connection failed: {}", e);
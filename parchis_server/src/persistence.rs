@@ -0,0 +1,157 @@
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+use parchis_core::events::GameEvent;
+use parchis_core::game_state::Game;
+
+/// Events appended to the journal before it is folded into a new snapshot.
+const SNAPSHOT_INTERVAL: usize = 25;
+
+/// Keeps a room's game recoverable after a crash.
+///
+/// The game loop calls `record` on every tick (`TICK_INTERVAL` in game_loop.rs)
+/// rather than after each command, so new events are appended to
+/// `room<id>.journal` as `<index> <event>` and synced in batches; a crash loses
+/// at most the events of the last tick. Every `SNAPSHOT_INTERVAL` events, and
+/// whenever a player joins (joins carry session tokens the journal does not),
+/// the whole game is written to `room<id>.snapshot` and the journal restarts.
+pub struct Journal {
+    snapshot_path: PathBuf,
+    journal_path: PathBuf,
+    /// Number of game events already on disk.
+    written: usize,
+    since_snapshot: usize,
+}
+
+impl Journal {
+    pub fn new(dir: &Path, room_id: usize) -> Journal {
+        Journal {
+            snapshot_path: dir.join(format!("room{}.snapshot", room_id)),
+            journal_path: dir.join(format!("room{}.journal", room_id)),
+            written: 0,
+            since_snapshot: 0,
+        }
+    }
+
+    /// Writes whatever happened in `game` since the last call.
+    pub fn record(&mut self, game: &Game) -> io::Result<()> {
+        let new_events = &game.events()[self.written.min(game.events().len())..];
+        if new_events.is_empty() {
+            return Ok(());
+        }
+
+        let joined = new_events.iter().any(|event| matches!(event, GameEvent::Joined { .. }));
+        if self.written == 0 || joined || self.since_snapshot + new_events.len() >= SNAPSHOT_INTERVAL {
+            return self.snapshot(game);
+        }
+
+        let mut journal = OpenOptions::new().create(true).append(true).open(&self.journal_path)?;
+        for (offset, event) in new_events.iter().enumerate() {
+            writeln!(journal, "{} {}", self.written + offset, event)?;
+        }
+        journal.sync_data()?;
+        self.written = game.events().len();
+        self.since_snapshot += new_events.len();
+        Ok(())
+    }
+
+    /// Replaces the snapshot atomically, then empties the journal.
+    fn snapshot(&mut self, game: &Game) -> io::Result<()> {
+        if let Some(dir) = self.snapshot_path.parent() {
+            fs::create_dir_all(dir)?;
+        }
+        let temporary = self.snapshot_path.with_extension("snapshot.tmp");
+        let mut file = File::create(&temporary)?;
+        file.write_all(game.to_save().as_bytes())?;
+        file.sync_all()?;
+        fs::rename(&temporary, &self.snapshot_path)?;
+
+        File::create(&self.journal_path)?.sync_all()?;
+        self.written = game.events().len();
+        self.since_snapshot = 0;
+        Ok(())
+    }
+
+    /// Loads the snapshot and applies the journal entries written after it.
+    fn replay(&mut self) -> io::Result<Game> {
+        let mut game = Game::load(&self.snapshot_path)?;
+        let journal = match fs::read_to_string(&self.journal_path) {
+            Ok(journal) => journal,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => String::new(),
+            Err(e) => return Err(e),
+        };
+
+        for line in journal.lines() {
+            // A crash mid-write can leave a torn last line; everything before it still counts.
+            let Some((index, event)) = line.split_once(' ') else {
+                break;
+            };
+            let (Ok(index), Some(event)) = (index.parse::<usize>(), GameEvent::parse(event)) else {
+                break;
+            };
+            // Entries from before a snapshot that was written right before a crash.
+            if index < game.events().len() {
+                continue;
+            }
+            game.apply_event(event).map_err(|reason| io::Error::new(io::ErrorKind::InvalidData, reason))?;
+        }
+
+        self.written = game.events().len();
+        self.since_snapshot = SNAPSHOT_INTERVAL;
+        Ok(game)
+    }
+
+    /// Renames the room's files to `*.failed`, so a new room with the same id
+    /// does not overwrite them.
+    fn set_aside(&self) {
+        for path in [&self.snapshot_path, &self.journal_path] {
+            let mut failed = path.clone().into_os_string();
+            failed.push(".failed");
+            match fs::rename(path, &failed) {
+                Ok(()) => eprintln!("Kept {} as {}", path.display(), PathBuf::from(&failed).display()),
+                Err(e) if e.kind() == io::ErrorKind::NotFound => {}
+                Err(e) => eprintln!("Could not set {} aside: {}", path.display(), e),
+            }
+        }
+    }
+
+    /// Deletes the room's files once its game no longer needs recovering.
+    pub fn remove(&self) {
+        let _ = fs::remove_file(&self.snapshot_path);
+        let _ = fs::remove_file(&self.journal_path);
+    }
+}
+
+/// Rebuilds every game found in `dir` from its snapshot and journal, returning
+/// them with their room ids and journals ready to carry on. A room that cannot
+/// be rebuilt is logged and skipped.
+pub fn restore(dir: &Path) -> io::Result<Vec<(usize, Game, Journal)>> {
+    let entries = match fs::read_dir(dir) {
+        Ok(entries) => entries,
+        Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(Vec::new()),
+        Err(e) => return Err(e),
+    };
+
+    let mut restored = Vec::new();
+    for entry in entries {
+        let path = entry?.path();
+        let room_id = path.file_name()
+            .and_then(|name| name.to_str())
+            .and_then(|name| name.strip_prefix("room")?.strip_suffix(".snapshot")?.parse().ok());
+        let Some(room_id) = room_id else {
+            continue;
+        };
+
+        let mut journal = Journal::new(dir, room_id);
+        match journal.replay() {
+            Ok(game) => restored.push((room_id, game, journal)),
+            // The other rooms still come back; this one's files are kept for a look.
+            Err(e) => {
+                eprintln!("Could not restore room {} from {}: {}", room_id, path.display(), e);
+                journal.set_aside();
+            }
+        }
+    }
+    restored.sort_by_key(|(room_id, _, _)| *room_id);
+    Ok(restored)
+}
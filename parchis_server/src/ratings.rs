@@ -0,0 +1,68 @@
+/// Rating every account starts with.
+pub const INITIAL_RATING: f64 = 1500.0;
+
+/// Most a rating can move in one game.
+const K_FACTOR: f64 = 32.0;
+
+/// Rating changes for a finished game, given the players' ratings from first to
+/// last place.
+///
+/// A free-for-all is scored as if every player had played every other one: a
+/// win against everyone placed below, a loss against everyone placed above.
+/// Each pairing counts `1 / (players - 1)`, so a game is worth as much as a
+/// one-on-one whatever the number of players.
+pub fn rating_changes(ratings: &[f64]) -> Vec<f64> {
+    let opponents = ratings.len().saturating_sub(1);
+    if opponents == 0 {
+        return vec![0.0; ratings.len()];
+    }
+
+    ratings.iter().enumerate().map(|(place, rating)| {
+        let change: f64 = ratings.iter().enumerate()
+            .filter(|(other_place, _)| *other_place != place)
+            .map(|(other_place, other_rating)| {
+                let score = if place < other_place { 1.0 } else { 0.0 };
+                let expected = 1.0 / (1.0 + 10f64.powf((other_rating - rating) / 400.0));
+                score - expected
+            })
+            .sum();
+        K_FACTOR * change / opponents as f64
+    }).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn close(a: f64, b: f64) -> bool {
+        (a - b).abs() < 1e-9
+    }
+
+    #[test]
+    fn even_one_on_one() {
+        let changes = rating_changes(&[1500.0, 1500.0]);
+        assert!(close(changes[0], 16.0) && close(changes[1], -16.0));
+    }
+
+    #[test]
+    fn upsets_move_ratings_more() {
+        let upset = rating_changes(&[1300.0, 1700.0]);
+        let expected = rating_changes(&[1700.0, 1300.0]);
+        assert!(upset[0] > 16.0 && expected[0] < 16.0);
+    }
+
+    #[test]
+    fn ratings_are_only_passed_around() {
+        let changes = rating_changes(&[1620.0, 1480.0, 1510.0, 1390.0]);
+        assert!(close(changes.iter().sum(), 0.0));
+        assert!(changes[0] > 0.0 && changes[3] < 0.0);
+        // Nobody moves further than in a one-on-one.
+        assert!(changes.iter().all(|change| change.abs() <= K_FACTOR));
+    }
+
+    #[test]
+    fn a_lone_player_keeps_their_rating() {
+        assert_eq!(rating_changes(&[1500.0]), vec![0.0]);
+        assert!(rating_changes(&[]).is_empty());
+    }
+}
@@ -0,0 +1,126 @@
+/// Where a connection is in its life at the server. The lobby works it out from
+/// the rooms on every command, so it can never disagree with the game itself.
+#[derive(Debug, PartialEq)]
+pub enum Session {
+    /// Not at any table yet.
+    Connected,
+    /// Seated, still without a color.
+    Joined { name: String },
+    /// Seated with a color, waiting for the game to start.
+    ColorChosen { name: String },
+    /// Seated in a game that has started, or has just finished.
+    Playing { name: String },
+    Spectating,
+}
+
+impl Session {
+    /// Checks that `command` makes sense at this point, returning the reason
+    /// it is refused otherwise. Commands this table does not know are let
+    /// through for the game to answer.
+    pub fn allows(&self, command: &str) -> Result<(), String> {
+        let refusal = match (self, command) {
+            (_, "REGISTER" | "LOGIN" | "NAME" | "STATS" | "HISTORY" | "LEADERBOARD" | "DICE_STATS") => return Ok(()),
+
+            (Session::Connected, "JOIN" | "WATCH" | "RESUME") => return Ok(()),
+            (Session::Connected, _) => "Join a game first with 'JOIN <name>', or watch one with 'WATCH [room]'.".to_string(),
+
+            (Session::Spectating, "WHO" | "SAY" | "WHISPER" | "LEAVE") => return Ok(()),
+            (Session::Spectating, _) => "Spectators cannot play. Type 'LEAVE' to stop watching.".to_string(),
+
+            (Session::Joined { name } | Session::ColorChosen { name }, "JOIN" | "WATCH" | "RESUME") => {
+                format!("You are already seated as {}. Type 'LEAVE' to leave this table first.", name)
+            }
+            (Session::Playing { name }, "JOIN" | "WATCH" | "RESUME") => format!("You are already playing as {}.", name),
+            (Session::Joined { .. }, "READY" | "BEGIN") => "Choose a color first with 'COLOR <color>'.".to_string(),
+            (Session::Joined { .. } | Session::ColorChosen { .. }, "ROLL" | "MOVE" | "MOVE_OUT" | "ENTROPY" | "SAVE" | "REMATCH" | "END") => {
+                "The game hasn't started yet.".to_string()
+            }
+            (Session::Playing { .. }, "COLOR" | "READY" | "KICK" | "HOST") => "The game has already started.".to_string(),
+            _ => return Ok(()),
+        };
+        Err(refusal)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn seated(name: &str) -> [Session; 3] {
+        let name = name.to_string();
+        [
+            Session::Joined { name: name.clone() },
+            Session::ColorChosen { name: name.clone() },
+            Session::Playing { name },
+        ]
+    }
+
+    #[test]
+    fn account_commands_are_allowed_everywhere() {
+        let mut sessions = Vec::from(seated("ana"));
+        sessions.extend([Session::Connected, Session::Spectating]);
+        for session in &sessions {
+            for command in ["REGISTER", "LOGIN", "NAME", "STATS", "HISTORY", "LEADERBOARD", "DICE_STATS"] {
+                assert_eq!(session.allows(command), Ok(()), "{:?} {}", session, command);
+            }
+        }
+    }
+
+    #[test]
+    fn a_new_connection_can_only_join_watch_or_resume() {
+        for command in ["JOIN", "WATCH", "RESUME"] {
+            assert_eq!(Session::Connected.allows(command), Ok(()));
+        }
+        for command in ["ROLL", "SAY", "LEAVE", "COLOR"] {
+            assert!(Session::Connected.allows(command).is_err(), "{}", command);
+        }
+    }
+
+    #[test]
+    fn spectators_can_talk_but_not_play() {
+        for command in ["WHO", "SAY", "WHISPER", "LEAVE"] {
+            assert_eq!(Session::Spectating.allows(command), Ok(()));
+        }
+        for command in ["ROLL", "COLOR", "READY", "JOIN"] {
+            assert!(Session::Spectating.allows(command).is_err(), "{}", command);
+        }
+    }
+
+    #[test]
+    fn seated_players_cannot_take_a_second_seat() {
+        for session in seated("ana") {
+            for command in ["JOIN", "WATCH", "RESUME"] {
+                let refusal = session.allows(command).unwrap_err();
+                assert!(refusal.contains("ana"), "{}", refusal);
+            }
+        }
+    }
+
+    #[test]
+    fn commands_follow_the_phase_of_the_game() {
+        let [joined, color_chosen, playing] = seated("ana");
+
+        assert!(joined.allows("READY").is_err());
+        assert!(joined.allows("BEGIN").is_err());
+        assert_eq!(joined.allows("COLOR"), Ok(()));
+        assert_eq!(color_chosen.allows("READY"), Ok(()));
+        assert_eq!(color_chosen.allows("BEGIN"), Ok(()));
+
+        for command in ["ROLL", "MOVE", "MOVE_OUT", "ENTROPY", "SAVE", "REMATCH", "END"] {
+            assert!(joined.allows(command).is_err(), "{}", command);
+            assert!(color_chosen.allows(command).is_err(), "{}", command);
+            assert_eq!(playing.allows(command), Ok(()));
+        }
+        for command in ["COLOR", "READY", "KICK", "HOST"] {
+            assert!(playing.allows(command).is_err(), "{}", command);
+        }
+    }
+
+    #[test]
+    fn unknown_commands_are_left_to_the_game() {
+        assert_eq!(Session::Connected.allows("FOO"), Err("Join a game first with 'JOIN <name>', or watch one with 'WATCH [room]'.".to_string()));
+        for session in seated("ana") {
+            assert_eq!(session.allows("FOO"), Ok(()));
+        }
+    }
+}